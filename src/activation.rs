@@ -0,0 +1,149 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::env;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::str::FromStr;
+use libc::{c_int, pid_t};
+use crate::option::{Level, Name};
+use crate::{Domain, Protocol, RawSocket, Type};
+
+/// First descriptor passed by systemd socket activation.
+pub const LISTEN_FDS_START: RawFd = 3;
+
+/// Socket inherited through systemd socket activation.
+pub struct Listener {
+    sock:     RawSocket,
+    name:     Option<String>,
+    domain:   c_int,
+    kind:     c_int,
+    protocol: c_int,
+}
+
+/// Take ownership of the sockets passed by systemd in `LISTEN_FDS`, named
+/// by `LISTEN_FDNAMES`. Returns no sockets when `LISTEN_PID` is missing or
+/// names another process. With `unset_env` the variables are removed so
+/// children and later calls do not claim the same descriptors. Changing
+/// the environment races with any other thread reading it, so call this
+/// with `unset_env` before starting threads.
+///
+/// Each descriptor has its own result, and one that cannot be claimed as a
+/// socket is left open and untouched.
+pub fn listen_fds(unset_env: bool) -> Result<Vec<Result<Listener>>> {
+    let pid   = env::var("LISTEN_PID");
+    let fds   = env::var("LISTEN_FDS");
+    let names = env::var("LISTEN_FDNAMES").unwrap_or_default();
+
+    if unset_env {
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+    }
+
+    let (pid, fds) = match (pid, fds) {
+        (Ok(pid), Ok(fds)) => (parse::<pid_t>("LISTEN_PID", &pid)?, parse::<c_int>("LISTEN_FDS", &fds)?),
+        _                  => return Ok(Vec::new()),
+    };
+
+    if pid != unsafe { libc::getpid() } {
+        return Ok(Vec::new());
+    }
+
+    let mut names = names.split(':').map(|name| match name {
+        ""   => None,
+        name => Some(name.to_owned()),
+    });
+
+    let end = LISTEN_FDS_START.checked_add(fds).ok_or_else(|| {
+        Error::new(ErrorKind::InvalidData, format!("invalid LISTEN_FDS: {}", fds))
+    })?;
+
+    Ok((LISTEN_FDS_START..end).map(|fd| {
+        Listener::new(fd, names.next().flatten())
+    }).collect())
+}
+
+impl Listener {
+    fn new(fd: RawFd, name: Option<String>) -> Result<Self> {
+        // only claim the descriptor once every check passes
+        let sock = ManuallyDrop::new(unsafe { RawSocket::from_raw_fd(fd) });
+
+        let domain   = sock.get_sockopt(Level::SOCKET, Name::SO_DOMAIN)?;
+        let kind     = sock.get_sockopt(Level::SOCKET, Name::SO_TYPE)?;
+        let protocol = sock.get_sockopt(Level::SOCKET, Name::SO_PROTOCOL)?;
+
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let sock = ManuallyDrop::into_inner(sock);
+
+        Ok(Self { sock, name, domain, kind, protocol })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn domain(&self) -> Domain {
+        self.domain.into()
+    }
+
+    pub fn kind(&self) -> Type {
+        self.kind.into()
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol.into()
+    }
+
+    /// Whether the socket has this domain, type, and protocol. Any protocol
+    /// matches when `protocol` is `None`.
+    pub fn is(&self, domain: Domain, kind: Type, protocol: Option<Protocol>) -> bool {
+        let protocol = protocol.map(c_int::from);
+        self.domain == domain.into()
+            && self.kind == kind.into()
+            && protocol.is_none_or(|protocol| self.protocol == protocol)
+    }
+
+    /// Unwrap the socket, failing if it is not the expected kind.
+    pub fn expect(self, domain: Domain, kind: Type, protocol: Option<Protocol>) -> Result<RawSocket> {
+        match self.is(domain, kind, protocol) {
+            true  => Ok(self.sock),
+            false => Err(Error::new(ErrorKind::InvalidInput, format!("unexpected {}", self))),
+        }
+    }
+
+    pub fn into_socket(self) -> RawSocket {
+        self.sock
+    }
+
+    #[cfg(feature = "async-tokio")]
+    pub fn into_tokio(self) -> Result<crate::tokio::RawSocket> {
+        crate::tokio::RawSocket::from_sys(self.sock)
+    }
+}
+
+fn parse<T: FromStr>(var: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| {
+        Error::new(ErrorKind::InvalidData, format!("invalid {}: {:?}", var, value))
+    })
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "socket {:?}", name)?,
+            None       => write!(f, "socket {}", self.sock.as_raw_fd())?,
+        }
+        write!(f, " (domain {}, type {}, protocol {})", self.domain, self.kind, self.protocol)
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
+use libc::{AF_INET, AF_INET6, c_int};
+use socket2::SockAddr;
+
+#[cfg(target_os = "linux")]
+pub use self::link::LinkAddr;
+#[cfg(target_os = "linux")]
+pub use self::netlink::NetlinkAddr;
+
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MacAddr([u8; 6]);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AddrParseError(());
+
+pub trait ToSockAddr {
+    fn to_sockaddr(&self) -> Result<SockAddr>;
+}
+
+pub trait FromSockAddr: Sized {
+    fn from_sockaddr(addr: &SockAddr) -> Result<Self>;
+}
+
+impl<T: ToSocketAddrs + ?Sized> ToSockAddr for T {
+    fn to_sockaddr(&self) -> Result<SockAddr> {
+        match self.to_socket_addrs()?.next() {
+            Some(addr) => Ok(SockAddr::from(addr)),
+            None       => Err(Error::new(ErrorKind::InvalidInput, "invalid socket address")),
+        }
+    }
+}
+
+impl FromSockAddr for SockAddr {
+    fn from_sockaddr(addr: &SockAddr) -> Result<Self> {
+        Ok(unsafe { SockAddr::from_raw_parts(addr.as_ptr(), addr.len()) })
+    }
+}
+
+impl FromSockAddr for SocketAddr {
+    fn from_sockaddr(addr: &SockAddr) -> Result<Self> {
+        match addr.family() as c_int {
+            AF_INET  => Ok(addr.as_inet().expect("AF_INET addr").into()),
+            AF_INET6 => Ok(addr.as_inet6().expect("AF_INET6 addr").into()),
+            _        => Err(unknown()),
+        }
+    }
+}
+
+impl MacAddr {
+    pub const BROADCAST: MacAddr = MacAddr([0xff; 6]);
+    pub const ZERO:      MacAddr = MacAddr([0x00; 6]);
+
+    pub const fn new(a: u8, b: u8, c: u8, d: u8, e: u8, f: u8) -> Self {
+        Self([a, b, c, d, e, f])
+    }
+
+    pub const fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    pub fn is_unicast(&self) -> bool {
+        !self.is_multicast()
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+}
+
+impl From<MacAddr> for [u8; 6] {
+    fn from(mac: MacAddr) -> Self {
+        mac.0
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut octets = [0u8; 6];
+        let mut parts  = s.split([':', '-']);
+
+        for octet in octets.iter_mut() {
+            let part = parts.next().ok_or(AddrParseError(()))?;
+            if part.len() != 2 {
+                return Err(AddrParseError(()));
+            }
+            *octet = u8::from_str_radix(part, 16).map_err(|_| AddrParseError(()))?;
+        }
+
+        match parts.next() {
+            Some(_) => Err(AddrParseError(())),
+            None    => Ok(Self(octets)),
+        }
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", a, b, c, d, e, g)
+    }
+}
+
+impl fmt::Debug for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for AddrParseError {}
+
+impl fmt::Display for AddrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid MAC address syntax")
+    }
+}
+
+/// Empty address for sending on a connected socket.
+pub(crate) fn unnamed() -> SockAddr {
+    let addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    unsafe { SockAddr::from_raw_parts(&addr as *const _ as *const _, 0) }
+}
+
+fn unknown() -> Error {
+    Error::other("unknown address type")
+}
+
+#[cfg(target_os = "linux")]
+mod link {
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::io::Result;
+    use std::mem::{size_of, zeroed};
+    use std::ptr;
+    use libc::c_int;
+    use socket2::SockAddr;
+    use crate::ffi::{self, AF_PACKET, sockaddr_ll};
+    use super::{FromSockAddr, MacAddr, ToSockAddr, unknown};
+
+    #[derive(Copy, Clone)]
+    pub struct LinkAddr(sockaddr_ll);
+
+    impl LinkAddr {
+        pub const HOST:      u8 = ffi::PACKET_HOST;
+        pub const BROADCAST: u8 = ffi::PACKET_BROADCAST;
+        pub const MULTICAST: u8 = ffi::PACKET_MULTICAST;
+        pub const OTHERHOST: u8 = ffi::PACKET_OTHERHOST;
+        pub const OUTGOING:  u8 = ffi::PACKET_OUTGOING;
+
+        pub fn new(protocol: u16, ifindex: u32) -> Self {
+            let mut sll: sockaddr_ll = unsafe { zeroed() };
+            sll.sll_family   = AF_PACKET as _;
+            sll.sll_protocol = protocol.to_be();
+            sll.sll_ifindex  = ifindex as _;
+            Self(sll)
+        }
+
+        pub fn with_addr(mut self, addr: &[u8]) -> Self {
+            let n = addr.len().min(self.0.sll_addr.len());
+            self.0.sll_addr[..n].copy_from_slice(&addr[..n]);
+            self.0.sll_halen = n as _;
+            self
+        }
+
+        pub fn with_mac(self, mac: MacAddr) -> Self {
+            self.with_addr(&mac.octets())
+        }
+
+        pub fn protocol(&self) -> u16 {
+            u16::from_be(self.0.sll_protocol)
+        }
+
+        pub fn ifindex(&self) -> u32 {
+            self.0.sll_ifindex as u32
+        }
+
+        pub fn hatype(&self) -> u16 {
+            self.0.sll_hatype
+        }
+
+        pub fn pkttype(&self) -> u8 {
+            self.0.sll_pkttype
+        }
+
+        pub fn addr(&self) -> &[u8] {
+            let n = usize::from(self.0.sll_halen).min(self.0.sll_addr.len());
+            &self.0.sll_addr[..n]
+        }
+
+        pub fn mac(&self) -> Option<MacAddr> {
+            let octets = <[u8; 6]>::try_from(self.addr()).ok()?;
+            Some(MacAddr::from(octets))
+        }
+    }
+
+    impl ToSockAddr for LinkAddr {
+        fn to_sockaddr(&self) -> Result<SockAddr> {
+            let ptr = &self.0 as *const _ as *const _;
+            let len = size_of::<sockaddr_ll>() as _;
+            Ok(unsafe { SockAddr::from_raw_parts(ptr, len) })
+        }
+    }
+
+    impl FromSockAddr for LinkAddr {
+        fn from_sockaddr(addr: &SockAddr) -> Result<Self> {
+            if addr.family() as c_int != AF_PACKET {
+                return Err(unknown());
+            }
+
+            let len = (addr.len() as usize).min(size_of::<sockaddr_ll>());
+
+            unsafe {
+                let mut sll: sockaddr_ll = zeroed();
+                let src = addr.as_ptr() as *const u8;
+                let dst = &mut sll as *mut _ as *mut u8;
+                ptr::copy_nonoverlapping(src, dst, len);
+                Ok(Self(sll))
+            }
+        }
+    }
+
+    impl fmt::Debug for LinkAddr {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let proto = self.protocol();
+            let ifidx = self.ifindex();
+            let addr  = self.addr();
+            write!(f, "{{ protocol: {:#06x}, ifindex: {}, addr: {:02x?} }}", proto, ifidx, addr)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod netlink {
+    use std::fmt;
+    use std::io::Result;
+    use std::mem::{size_of, zeroed};
+    use std::ptr;
+    use libc::c_int;
+    use socket2::SockAddr;
+    use crate::ffi::{AF_NETLINK, sockaddr_nl};
+    use super::{FromSockAddr, ToSockAddr, unknown};
+
+    #[derive(Copy, Clone)]
+    pub struct NetlinkAddr(sockaddr_nl);
+
+    impl NetlinkAddr {
+        pub fn new(pid: u32, groups: u32) -> Self {
+            let mut snl: sockaddr_nl = unsafe { zeroed() };
+            snl.nl_family = AF_NETLINK as _;
+            snl.nl_pid    = pid;
+            snl.nl_groups = groups;
+            Self(snl)
+        }
+
+        pub fn kernel() -> Self {
+            Self::new(0, 0)
+        }
+
+        pub fn pid(&self) -> u32 {
+            self.0.nl_pid
+        }
+
+        pub fn groups(&self) -> u32 {
+            self.0.nl_groups
+        }
+    }
+
+    impl ToSockAddr for NetlinkAddr {
+        fn to_sockaddr(&self) -> Result<SockAddr> {
+            let ptr = &self.0 as *const _ as *const _;
+            let len = size_of::<sockaddr_nl>() as _;
+            Ok(unsafe { SockAddr::from_raw_parts(ptr, len) })
+        }
+    }
+
+    impl FromSockAddr for NetlinkAddr {
+        fn from_sockaddr(addr: &SockAddr) -> Result<Self> {
+            if addr.family() as c_int != AF_NETLINK {
+                return Err(unknown());
+            }
+
+            let len = (addr.len() as usize).min(size_of::<sockaddr_nl>());
+
+            unsafe {
+                let mut snl: sockaddr_nl = zeroed();
+                let src = addr.as_ptr() as *const u8;
+                let dst = &mut snl as *mut _ as *mut u8;
+                ptr::copy_nonoverlapping(src, dst, len);
+                Ok(Self(snl))
+            }
+        }
+    }
+
+    impl PartialEq for NetlinkAddr {
+        fn eq(&self, other: &Self) -> bool {
+            (self.pid(), self.groups()) == (other.pid(), other.groups())
+        }
+    }
+
+    impl Eq for NetlinkAddr {}
+
+    impl fmt::Debug for NetlinkAddr {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{{ pid: {}, groups: {:#x} }}", self.pid(), self.groups())
+        }
+    }
+}
@@ -0,0 +1,7 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+pub use self::socket::RawSocket;
+
+pub mod prelude;
+
+mod socket;
@@ -0,0 +1,217 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::convert::TryFrom;
+use std::io::{self, IoSlice, IoSliceMut, Result};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::unix::prelude::*;
+use async_io::Async;
+use crate::addr::{FromSockAddr, ToSockAddr};
+#[cfg(target_os = "linux")]
+use crate::icmp;
+#[cfg(target_os = "linux")]
+use crate::iface::Interface;
+#[cfg(target_os = "linux")]
+use crate::netns::Netns;
+use crate::option::{Level, Name, Opt};
+use crate::{Domain, Protocol, Type};
+
+/// Async `RawSocket` driven by the `async-io` reactor, for use with smol
+/// and async-std.
+pub struct RawSocket {
+    io: Async<crate::RawSocket>,
+}
+
+impl RawSocket {
+    pub fn new(domain: Domain, kind: Type, protocol: Option<Protocol>) -> Result<Self> {
+        Self::from_sys(crate::RawSocket::new(domain, kind, protocol)?)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_in_netns<N: Into<Netns>>(
+        netns: N,
+        domain: Domain,
+        kind: Type,
+        protocol: Option<Protocol>,
+    ) -> Result<Self> {
+        Self::from_sys(crate::RawSocket::new_in_netns(netns, domain, kind, protocol)?)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_icmp(domain: Domain) -> Result<(Self, icmp::Mode)> {
+        let (sys, mode) = crate::RawSocket::new_icmp(domain)?;
+        Ok((Self::from_sys(sys)?, mode))
+    }
+
+    pub(crate) fn from_sys(sys: crate::RawSocket) -> Result<Self> {
+        let io = Async::new(sys)?;
+        Ok(RawSocket { io })
+    }
+
+    /// Create a new handle to the same socket by duplicating its descriptor.
+    pub fn try_clone(&self) -> Result<Self> {
+        Self::from_sys(self.io.get_ref().try_clone()?)
+    }
+
+    pub async fn bind<A: ToSockAddr>(&self, addr: A) -> Result<()> {
+        self.io.get_ref().bind(addr)
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    pub fn local_addr_as<A: FromSockAddr>(&self) -> Result<A> {
+        self.io.get_ref().local_addr_as()
+    }
+
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.recv_from_as(buf).await
+    }
+
+    pub async fn recv_from_as<A: FromSockAddr>(&self, buf: &mut [u8]) -> Result<(usize, A)> {
+        self.io.read_with(|s| s.recv_from_as(buf)).await
+    }
+
+    pub async fn recv_msg(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Result<(usize, SocketAddr)> {
+        self.recv_msg_as(data, ctrl).await
+    }
+
+    pub async fn recv_msg_as<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Result<(usize, A)> {
+        let ctrl = ctrl.unwrap_or(&mut []);
+        self.io.read_with(|s| s.recv_msg_as(data, ctrl)).await
+    }
+
+    pub async fn recv_msg_flags<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+        flags: libc::c_int,
+    ) -> Result<(usize, A)> {
+        let ctrl = ctrl.unwrap_or(&mut []);
+        self.io.read_with(|s| s.recv_msg_flags(data, ctrl, flags)).await
+    }
+
+    /// Receive a message along with ownership of any descriptors passed
+    /// with `SCM_RIGHTS`.
+    pub async fn recv_msg_fds<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A, Vec<OwnedFd>)> {
+        self.io.read_with(|s| s.recv_msg_fds(data, ctrl)).await
+    }
+
+    pub async fn send_to<A: ToSockAddr>(&self, buf: &[u8], addr: A) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        self.io.write_with(|s| s.send_to_addr(buf, &addr)).await
+    }
+
+    pub async fn send_msg<A: ToSockAddr>(
+        &self,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: Option<&[u8]>,
+    ) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        let ctrl = ctrl.unwrap_or(&[]);
+        self.io.write_with(|s| s.send_msg_addr(&addr, data, ctrl)).await
+    }
+
+    /// Wait until the socket may be readable.
+    pub async fn readable(&self) -> Result<()> {
+        self.io.readable().await
+    }
+
+    /// Wait until the socket may be writable.
+    pub async fn writable(&self) -> Result<()> {
+        self.io.writable().await
+    }
+
+    pub fn get_sockopt<O: Opt>(&self, level: Level, name: Name) -> Result<O> {
+        self.io.get_ref().get_sockopt(level, name)
+    }
+
+    pub fn set_sockopt<O: Opt>(&self, level: Level, name: Name, value: &O) -> Result<()> {
+        self.io.get_ref().set_sockopt(level, name, value)
+    }
+
+    pub fn join_multicast_v4(&self, addr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.io.get_ref().join_multicast_v4(addr, interface)
+    }
+
+    pub fn join_multicast_v6(&self, addr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.io.get_ref().join_multicast_v6(addr, interface)
+    }
+
+    pub fn leave_multicast_v4(&self, addr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.io.get_ref().leave_multicast_v4(addr, interface)
+    }
+
+    pub fn leave_multicast_v6(&self, addr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.io.get_ref().leave_multicast_v6(addr, interface)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_device(&self, interface: Option<&str>) -> Result<()> {
+        self.io.get_ref().bind_device(interface)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_ifindex(&self, ifindex: u32) -> Result<()> {
+        self.io.get_ref().bind_ifindex(ifindex)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_interface(&self, interface: &Interface) -> Result<()> {
+        self.io.get_ref().bind_interface(interface)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn device(&self) -> Result<Option<String>> {
+        self.io.get_ref().device()
+    }
+}
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl AsFd for RawSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.io.get_ref().as_fd()
+    }
+}
+
+impl TryFrom<crate::RawSocket> for RawSocket {
+    type Error = io::Error;
+
+    fn try_from(sys: crate::RawSocket) -> Result<Self> {
+        Self::from_sys(sys)
+    }
+}
+
+impl TryFrom<OwnedFd> for RawSocket {
+    type Error = io::Error;
+
+    fn try_from(fd: OwnedFd) -> Result<Self> {
+        Self::from_sys(fd.into())
+    }
+}
+
+impl TryFrom<RawSocket> for crate::RawSocket {
+    type Error = io::Error;
+
+    fn try_from(sock: RawSocket) -> Result<Self> {
+        sock.io.into_inner()
+    }
+}
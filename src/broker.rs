@@ -0,0 +1,247 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::convert::TryInto;
+use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Result};
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use libc::{c_int, c_uint, pid_t, uid_t};
+use socket2::SockAddr;
+use crate::addr::unnamed;
+use crate::control::{CMsg, Credentials};
+use crate::ffi;
+use crate::option::{Level, Name};
+use crate::{Domain, Protocol, RawSocket, Type};
+
+/// Domains, types, protocols, and users a broker will create sockets for.
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    allow: Vec<[c_int; 3]>,
+    uids:  Vec<uid_t>,
+}
+
+/// Privileged side of a broker connection, creating sockets on behalf of
+/// the peer and passing them back with `SCM_RIGHTS`.
+pub struct Broker {
+    sock:   RawSocket,
+    policy: Policy,
+}
+
+/// Unprivileged side of a broker connection.
+pub struct Client {
+    sock: RawSocket,
+    pid:  Option<pid_t>,
+}
+
+const REQUEST_SIZE: usize = 3 * size_of::<c_int>();
+const REPLY_SIZE:   usize = size_of::<c_int>();
+
+/// Connected pair of `SOCK_SEQPACKET` Unix sockets.
+pub fn pair() -> Result<(RawSocket, RawSocket)> {
+    let mut fds = [0; 2];
+
+    let kind = libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC;
+    if unsafe { libc::socketpair(libc::AF_UNIX, kind, 0, fds.as_mut_ptr()) } != 0 {
+        return Err(Error::last_os_error());
+    }
+
+    unsafe { Ok((RawSocket::from_raw_fd(fds[0]), RawSocket::from_raw_fd(fds[1]))) }
+}
+
+/// Fork a broker process enforcing `policy` and return a client connected
+/// to it. The broker exits when the client is dropped, and holds no other
+/// descriptors of this process.
+///
+/// The broker runs without `exec`, so this must be called before any
+/// threads start. A lock held by another thread at the time of the fork,
+/// such as the allocator's, would never be released in the broker.
+pub fn spawn(policy: Policy) -> Result<Client> {
+    let (client, broker) = pair()?;
+
+    match unsafe { libc::fork() } {
+        -1  => Err(Error::last_os_error()),
+        0   => unsafe {
+            close_fds_except(broker.as_raw_fd());
+            // closed along with everything else the parent had open
+            std::mem::forget(client);
+            let code = match Broker::new(broker, policy).and_then(|b| b.serve()) {
+                Ok(()) => 0,
+                Err(_) => 1,
+            };
+            libc::_exit(code)
+        },
+        pid => Ok(Client { sock: client, pid: Some(pid) }),
+    }
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, domain: Domain, kind: Type, protocol: Option<Protocol>) -> Self {
+        let protocol = protocol.map(c_int::from).unwrap_or(0);
+        self.allow.push([domain.into(), kind.into(), protocol]);
+        self
+    }
+
+    /// Restrict the broker to peers running as `uid`. Any user is allowed
+    /// if no uids are given.
+    pub fn allow_uid(mut self, uid: uid_t) -> Self {
+        self.uids.push(uid);
+        self
+    }
+
+    pub fn permits(&self, request: [c_int; 3], cred: Option<&Credentials>) -> bool {
+        let user = match cred {
+            Some(cred) => self.uids.is_empty() || self.uids.contains(&cred.uid()),
+            None       => self.uids.is_empty(),
+        };
+        user && self.allow.contains(&request)
+    }
+}
+
+impl Broker {
+    pub fn new(sock: RawSocket, policy: Policy) -> Result<Self> {
+        let enable: c_int = 1;
+        sock.set_sockopt(Level::SOCKET, Name::SO_PASSCRED, &enable)?;
+        Ok(Self { sock, policy })
+    }
+
+    /// Handle requests until the client disconnects.
+    pub fn serve(&self) -> Result<()> {
+        while self.handle()? {}
+        Ok(())
+    }
+
+    fn handle(&self) -> Result<bool> {
+        let mut buf  = [0u8; REQUEST_SIZE];
+        let mut ctrl = [0u8; 128];
+
+        let iovec = &[IoSliceMut::new(&mut buf)];
+        let (n, _) = self.sock.recv_msg_flags::<SockAddr>(iovec, &mut ctrl, libc::MSG_CMSG_CLOEXEC)?;
+
+        if n == 0 {
+            return Ok(false);
+        } else if n != REQUEST_SIZE {
+            return self.reply(Err(Error::from_raw_os_error(libc::EINVAL))).map(|_| true);
+        }
+
+        let cred = CMsg::decode(&ctrl).find_map(|msg| match msg {
+            CMsg::ScmCredentials(cred) => Some(cred),
+            _                          => None,
+        });
+
+        let mut request = [0; 3];
+        for (value, bytes) in request.iter_mut().zip(buf.chunks(size_of::<c_int>())) {
+            *value = c_int::from_ne_bytes(bytes.try_into().expect("c_int"));
+        }
+
+        let result = match self.policy.permits(request, cred.as_ref()) {
+            true  => RawSocket::new(request[0].into(), request[1].into(), Some(request[2].into())),
+            false => Err(Error::from_raw_os_error(libc::EACCES)),
+        };
+
+        self.reply(result)?;
+
+        Ok(true)
+    }
+
+    fn reply(&self, result: Result<RawSocket>) -> Result<()> {
+        let errno = match &result {
+            Ok(..) => 0,
+            Err(e) => e.raw_os_error().unwrap_or(libc::EIO),
+        };
+
+        let mut ctrl = [0u8; 128];
+        let ctrl = match &result {
+            Ok(sock) => CMsg::encode(&mut ctrl, &[CMsg::ScmRights(&[sock.as_raw_fd()])])?,
+            Err(..)  => &[],
+        };
+
+        let data = errno.to_ne_bytes();
+        self.sock.send_msg_addr(&unnamed(), &[IoSlice::new(&data)], ctrl)?;
+
+        Ok(())
+    }
+}
+
+impl Client {
+    pub fn new(sock: RawSocket) -> Self {
+        Self { sock, pid: None }
+    }
+
+    /// Ask the broker for a new socket. Takes `&mut self` so each reply is
+    /// received by the request it answers.
+    pub fn open(&mut self, domain: Domain, kind: Type, protocol: Option<Protocol>) -> Result<RawSocket> {
+        let protocol = protocol.map(c_int::from).unwrap_or(0);
+
+        let mut req = [0u8; REQUEST_SIZE];
+        let values  = [domain.into(), kind.into(), protocol];
+        for (bytes, value) in req.chunks_mut(size_of::<c_int>()).zip(values.iter()) {
+            bytes.copy_from_slice(&c_int::to_ne_bytes(*value));
+        }
+
+        let mut ctrl = [0u8; 128];
+        let cred = CMsg::encode(&mut ctrl, &[Credentials::current().into()])?;
+        self.sock.send_msg_addr(&unnamed(), &[IoSlice::new(&req)], cred)?;
+
+        let mut buf  = [0u8; REPLY_SIZE];
+        let mut ctrl = [0u8; 128];
+
+        let iovec = &[IoSliceMut::new(&mut buf)];
+        let (n, _, fds) = self.sock.recv_msg_fds::<SockAddr>(iovec, &mut ctrl)?;
+
+        if n != REPLY_SIZE {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "broker closed connection"));
+        }
+
+        let fd = fds.into_iter().next();
+
+        match (c_int::from_ne_bytes(buf), fd) {
+            (0, Some(fd)) => Ok(RawSocket::from(fd)),
+            (0, None)     => Err(Error::new(ErrorKind::InvalidData, "missing descriptor")),
+            (errno, _)    => Err(Error::from_raw_os_error(errno)),
+        }
+    }
+
+    #[cfg(feature = "async-tokio")]
+    pub fn open_tokio(&mut self, domain: Domain, kind: Type, protocol: Option<Protocol>) -> Result<crate::tokio::RawSocket> {
+        crate::tokio::RawSocket::from_sys(self.open(domain, kind, protocol)?)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid {
+            let _ = self.sock.shutdown(std::net::Shutdown::Both);
+            unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) };
+        }
+    }
+}
+
+impl AsRawFd for Client {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+/// Close every descriptor but `keep`, falling back to closing each one up
+/// to the descriptor limit on kernels without `close_range`.
+unsafe fn close_fds_except(keep: RawFd) {
+    let max = match libc::sysconf(libc::_SC_OPEN_MAX) {
+        n if n > 0 => n.min(RawFd::MAX.into()) as RawFd,
+        _          => 1024,
+    };
+
+    for (lo, hi) in [(0, keep - 1), (keep + 1, RawFd::MAX)] {
+        if lo > hi {
+            continue;
+        }
+
+        if libc::syscall(ffi::SYS_CLOSE_RANGE, lo as c_uint, hi as c_uint, 0 as c_uint) != 0 {
+            for fd in lo..=hi.min(max) {
+                libc::close(fd);
+            }
+        }
+    }
+}
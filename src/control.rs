@@ -2,22 +2,75 @@
 
 use std::fmt;
 use std::iter;
-use std::mem::{size_of, zeroed};
-use std::net::Ipv6Addr;
+use std::mem::{size_of, size_of_val, zeroed};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(target_os = "linux")]
+use std::ops::RangeInclusive;
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
 use std::ptr;
 use std::slice;
+use std::time::Duration;
+use libc::{gid_t, pid_t, uid_t};
 use crate::ffi::*;
+use crate::iface::Interface;
 
 #[derive(Debug)]
 pub enum CMsg<'a> {
     Ipv6HopLimit(c_int),
     Ipv6PathMtu(c_int),
     Ipv6PktInfo(Ipv6PktInfo),
+    #[cfg(target_os = "linux")]
+    Ipv4PktInfo(Ipv4PktInfo),
+    #[cfg(target_os = "linux")]
+    PacketAuxData(PacketAuxData),
+    /// Descriptors to send. This variant is send-only: received descriptors
+    /// are moved out of the control buffer, returned as owned by
+    /// `recv_msg_fds` and otherwise closed, so `decode` yields the emptied
+    /// `SCM_RIGHTS` message as `Raw` data holding -1s.
+    ScmRights(&'a [RawFd]),
+    #[cfg(target_os = "linux")]
+    ScmCredentials(Credentials),
+    #[cfg(target_os = "linux")]
+    ExtendedErr(ExtendedErr),
+    #[cfg(target_os = "linux")]
+    Timestamping(Timestamping),
+    #[cfg(target_os = "linux")]
+    UdpSegment(u16),
+    #[cfg(target_os = "linux")]
+    UdpGro(c_int),
+    #[cfg(target_os = "linux")]
+    TxTime(u64),
     Raw(Raw<'a>),
 }
 
 pub struct Ipv6PktInfo(in6_pktinfo);
 
+#[cfg(target_os = "linux")]
+pub struct Ipv4PktInfo(in_pktinfo);
+
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone)]
+pub struct PacketAuxData(tpacket_auxdata);
+
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone)]
+pub struct Credentials(ucred);
+
+/// Error queue entry from `IP_RECVERR` or `IPV6_RECVERR`, along with the
+/// address of the node that reported it.
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone)]
+pub struct ExtendedErr {
+    level:    c_int,
+    err:      sock_extended_err,
+    offender: Option<SocketAddr>,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone)]
+pub struct Timestamping(scm_timestamping);
+
 #[derive(Debug)]
 pub struct Raw<'a> {
     pub level: c_int,
@@ -69,6 +122,7 @@ impl<'a> CMsg<'a> {
                 let kind  = (*header).cmsg_type;
 
                 let ptr = CMSG_DATA(header);
+                #[allow(clippy::unnecessary_cast)]
                 let len = len as usize;
 
                 next = next_header(&root, header);
@@ -78,11 +132,61 @@ impl<'a> CMsg<'a> {
         }
     }
 
+    /// Take ownership of the descriptors in any `SCM_RIGHTS` messages in
+    /// `buf`, overwriting them with -1 so they cannot be taken twice.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must hold control messages just received from the kernel, so
+    /// its descriptors are open and owned by no one else.
+    pub(crate) unsafe fn take_fds(buf: &mut [u8]) -> Vec<OwnedFd> {
+        let mut fds  = Vec::new();
+        let mut root = message_header(buf);
+        let mut next = first_header(&mut root);
+
+        while let Ok(header) = next {
+            if (*header).cmsg_level == SOL_SOCKET && (*header).cmsg_type == SCM_RIGHTS {
+                #[allow(clippy::unnecessary_cast)]
+                let len = ((*header).cmsg_len as usize).saturating_sub(CMSG_LEN(0) as usize);
+                let ptr = CMSG_DATA(header) as *mut RawFd;
+
+                for i in 0..len / size_of::<RawFd>() {
+                    let fd = ptr::read_unaligned(ptr.add(i));
+                    if fd >= 0 {
+                        fds.push(OwnedFd::from_raw_fd(fd));
+                    }
+                    ptr::write_unaligned(ptr.add(i), -1);
+                }
+            }
+
+            next = next_header(&root, header);
+        }
+
+        fds
+    }
+
     fn level(&self) -> c_int {
         match self {
             Self::Ipv6HopLimit(..) => IPPROTO_IPV6,
             Self::Ipv6PathMtu(..)  => IPPROTO_IPV6,
             Self::Ipv6PktInfo(..)  => IPPROTO_IPV6,
+            #[cfg(target_os = "linux")]
+            Self::Ipv4PktInfo(..)  => IPPROTO_IP,
+            #[cfg(target_os = "linux")]
+            Self::PacketAuxData(..) => SOL_PACKET,
+            Self::ScmRights(..)    => SOL_SOCKET,
+            #[cfg(target_os = "linux")]
+            Self::ScmCredentials(..) => SOL_SOCKET,
+            #[cfg(target_os = "linux")]
+            Self::ExtendedErr(err) => err.level,
+            #[cfg(target_os = "linux")]
+            Self::Timestamping(..) => SOL_SOCKET,
+            #[cfg(target_os = "linux")]
+            Self::UdpSegment(..)   => SOL_UDP,
+            #[cfg(target_os = "linux")]
+            Self::UdpGro(..)       => SOL_UDP,
+            #[cfg(target_os = "linux")]
+            Self::TxTime(..)       => SOL_SOCKET,
             Self::Raw(raw)         => raw.level,
         }
     }
@@ -92,6 +196,26 @@ impl<'a> CMsg<'a> {
             Self::Ipv6HopLimit(..) => IPV6_HOPLIMIT,
             Self::Ipv6PathMtu(..)  => IPV6_PATHMTU,
             Self::Ipv6PktInfo(..)  => IPV6_PKTINFO,
+            #[cfg(target_os = "linux")]
+            Self::Ipv4PktInfo(..)  => IP_PKTINFO,
+            #[cfg(target_os = "linux")]
+            Self::PacketAuxData(..) => PACKET_AUXDATA,
+            Self::ScmRights(..)    => SCM_RIGHTS,
+            #[cfg(target_os = "linux")]
+            Self::ScmCredentials(..) => SCM_CREDENTIALS,
+            #[cfg(target_os = "linux")]
+            Self::ExtendedErr(err) => match err.level {
+                IPPROTO_IPV6 => IPV6_RECVERR,
+                _            => IP_RECVERR,
+            },
+            #[cfg(target_os = "linux")]
+            Self::Timestamping(..) => SCM_TIMESTAMPING,
+            #[cfg(target_os = "linux")]
+            Self::UdpSegment(..)   => UDP_SEGMENT,
+            #[cfg(target_os = "linux")]
+            Self::UdpGro(..)       => UDP_GRO,
+            #[cfg(target_os = "linux")]
+            Self::TxTime(..)       => SCM_TXTIME,
             Self::Raw(raw)         => raw.kind,
         }
     }
@@ -101,6 +225,23 @@ impl<'a> CMsg<'a> {
             Self::Ipv6HopLimit(..) => size_of::<c_int>(),
             Self::Ipv6PathMtu(..)  => size_of::<c_int>(),
             Self::Ipv6PktInfo(..)  => size_of::<in6_pktinfo>(),
+            #[cfg(target_os = "linux")]
+            Self::Ipv4PktInfo(..)  => size_of::<in_pktinfo>(),
+            #[cfg(target_os = "linux")]
+            Self::PacketAuxData(..) => size_of::<tpacket_auxdata>(),
+            Self::ScmRights(fds)   => size_of_val(*fds),
+            #[cfg(target_os = "linux")]
+            Self::ScmCredentials(..) => size_of::<ucred>(),
+            #[cfg(target_os = "linux")]
+            Self::ExtendedErr(..)  => size_of::<sock_extended_err>(),
+            #[cfg(target_os = "linux")]
+            Self::Timestamping(..) => size_of::<scm_timestamping>(),
+            #[cfg(target_os = "linux")]
+            Self::UdpSegment(..)   => size_of::<u16>(),
+            #[cfg(target_os = "linux")]
+            Self::UdpGro(..)       => size_of::<c_int>(),
+            #[cfg(target_os = "linux")]
+            Self::TxTime(..)       => size_of::<u64>(),
             Self::Raw(raw)         => raw.data.len(),
         }
     }
@@ -112,6 +253,24 @@ impl<'a> CMsg<'a> {
             (IPPROTO_IPV6, IPV6_HOPLIMIT) => CMsg::Ipv6HopLimit(read(ptr)),
             (IPPROTO_IPV6, IPV6_PATHMTU ) => CMsg::Ipv6PathMtu(read(ptr)),
             (IPPROTO_IPV6, IPV6_PKTINFO ) => Ipv6PktInfo(read(ptr)).into(),
+            #[cfg(target_os = "linux")]
+            (IPPROTO_IP  , IP_PKTINFO   ) => Ipv4PktInfo(read(ptr)).into(),
+            #[cfg(target_os = "linux")]
+            (SOL_PACKET, PACKET_AUXDATA)  => PacketAuxData(read(ptr)).into(),
+            #[cfg(target_os = "linux")]
+            (SOL_SOCKET, SCM_CREDENTIALS) => Credentials(read(ptr)).into(),
+            #[cfg(target_os = "linux")]
+            (IPPROTO_IP  , IP_RECVERR   ) => ExtendedErr::read(level, ptr, len).into(),
+            #[cfg(target_os = "linux")]
+            (IPPROTO_IPV6, IPV6_RECVERR ) => ExtendedErr::read(level, ptr, len).into(),
+            #[cfg(target_os = "linux")]
+            (SOL_SOCKET, SCM_TIMESTAMPING) => Timestamping(read(ptr)).into(),
+            #[cfg(target_os = "linux")]
+            (SOL_UDP     , UDP_SEGMENT  ) => CMsg::UdpSegment(read(ptr)),
+            #[cfg(target_os = "linux")]
+            (SOL_UDP     , UDP_GRO      ) => CMsg::UdpGro(read(ptr)),
+            #[cfg(target_os = "linux")]
+            (SOL_SOCKET  , SCM_TXTIME   ) => CMsg::TxTime(read(ptr)),
             (INVALID     , INVALID      ) => return None,
             (_           , _            ) => Raw::read(level, kind, ptr, len).into(),
         })
@@ -122,6 +281,23 @@ impl<'a> CMsg<'a> {
             Self::Ipv6HopLimit(limit) => write(ptr, limit.to_le()),
             Self::Ipv6PathMtu(mtu)    => write(ptr, mtu),
             Self::Ipv6PktInfo(info)   => write(ptr, info.0),
+            #[cfg(target_os = "linux")]
+            Self::Ipv4PktInfo(info)   => write(ptr, info.0),
+            #[cfg(target_os = "linux")]
+            Self::PacketAuxData(aux)  => write(ptr, aux.0),
+            Self::ScmRights(fds)      => ptr::copy_nonoverlapping(fds.as_ptr(), ptr as *mut RawFd, fds.len()),
+            #[cfg(target_os = "linux")]
+            Self::ScmCredentials(cred) => write(ptr, cred.0),
+            #[cfg(target_os = "linux")]
+            Self::ExtendedErr(err)    => write(ptr, err.err),
+            #[cfg(target_os = "linux")]
+            Self::Timestamping(ts)    => write(ptr, ts.0),
+            #[cfg(target_os = "linux")]
+            Self::UdpSegment(size)    => write(ptr, *size),
+            #[cfg(target_os = "linux")]
+            Self::UdpGro(size)        => write(ptr, *size),
+            #[cfg(target_os = "linux")]
+            Self::TxTime(time)        => write(ptr, *time),
             Self::Raw(raw)            => raw.write(ptr),
         }
     }
@@ -157,12 +333,271 @@ unsafe fn write<T>(dst: *mut u8, src: T) {
 }
 
 impl Ipv6PktInfo {
+    pub fn new(addr: Ipv6Addr, ifindex: u32) -> Self {
+        let mut info: in6_pktinfo = unsafe { zeroed() };
+        info.ipi6_addr.s6_addr = addr.octets();
+        info.ipi6_ifindex      = ifindex as _;
+        Self(info)
+    }
+
     pub fn addr(&self) -> Ipv6Addr {
         Ipv6Addr::from(self.0.ipi6_addr.s6_addr)
     }
 
     pub fn ifindex(&self) -> u32 {
-        self.0.ipi6_ifindex as u32
+        self.0.ipi6_ifindex
+    }
+
+    pub fn interface(&self) -> io::Result<Interface> {
+        Interface::from_index(self.ifindex())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Ipv4PktInfo {
+    pub fn new(spec_dst: Ipv4Addr, ifindex: u32) -> Self {
+        let mut info: in_pktinfo = unsafe { zeroed() };
+        info.ipi_spec_dst.s_addr = u32::from(spec_dst).to_be();
+        info.ipi_ifindex         = ifindex as _;
+        Self(info)
+    }
+
+    /// Destination address of a received packet.
+    pub fn addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from_be(self.0.ipi_addr.s_addr))
+    }
+
+    /// Local address used for routing and as the source of sent packets.
+    pub fn spec_dst(&self) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from_be(self.0.ipi_spec_dst.s_addr))
+    }
+
+    pub fn ifindex(&self) -> u32 {
+        self.0.ipi_ifindex as u32
+    }
+
+    pub fn interface(&self) -> io::Result<Interface> {
+        Interface::from_index(self.ifindex())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PacketAuxData {
+    const ETH_ALEN:    usize = 6;
+    const VLAN_HLEN:   usize = 4;
+    const ETH_P_8021Q: u16   = 0x8100;
+
+    pub fn status(&self) -> u32 {
+        self.0.tp_status
+    }
+
+    pub fn packet_len(&self) -> u32 {
+        self.0.tp_len
+    }
+
+    pub fn snaplen(&self) -> u32 {
+        self.0.tp_snaplen
+    }
+
+    pub fn mac(&self) -> u16 {
+        self.0.tp_mac
+    }
+
+    pub fn net(&self) -> u16 {
+        self.0.tp_net
+    }
+
+    pub fn vlan_tci(&self) -> Option<u16> {
+        match self.0.tp_status & TP_STATUS_VLAN_VALID {
+            0 => None,
+            _ => Some(self.0.tp_vlan_tci),
+        }
+    }
+
+    pub fn vlan_tpid(&self) -> Option<u16> {
+        self.vlan_tci()?;
+        match self.0.tp_status & TP_STATUS_VLAN_TPID_VALID {
+            0 => Some(Self::ETH_P_8021Q),
+            _ => Some(self.0.tp_vlan_tpid),
+        }
+    }
+
+    /// Reinsert the VLAN tag stripped by the kernel into an Ethernet frame
+    /// received on a `SOCK_RAW` packet socket, copying the result into `buf`.
+    /// Frames without a valid tag are copied unchanged.
+    pub fn restore_vlan<'b>(&self, frame: &[u8], buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+        let (tpid, tci) = match (self.vlan_tpid(), self.vlan_tci()) {
+            (Some(tpid), Some(tci)) if frame.len() >= 2 * Self::ETH_ALEN => (tpid, tci),
+            _ => return copy(frame, buf),
+        };
+
+        let n = frame.len() + Self::VLAN_HLEN;
+        let (macs, rest) = frame.split_at(2 * Self::ETH_ALEN);
+
+        let buf = buf.get_mut(..n).ok_or(Error::BufferSize)?;
+        let (head, tail) = buf.split_at_mut(macs.len());
+        let (tag,  tail) = tail.split_at_mut(Self::VLAN_HLEN);
+
+        head.copy_from_slice(macs);
+        tag[0..2].copy_from_slice(&tpid.to_be_bytes());
+        tag[2..4].copy_from_slice(&tci.to_be_bytes());
+        tail.copy_from_slice(rest);
+
+        Ok(buf)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn copy<'b>(src: &[u8], dst: &'b mut [u8]) -> Result<&'b [u8], Error> {
+    let dst = dst.get_mut(..src.len()).ok_or(Error::BufferSize)?;
+    dst.copy_from_slice(src);
+    Ok(dst)
+}
+
+#[cfg(target_os = "linux")]
+impl Credentials {
+    pub fn new(pid: pid_t, uid: uid_t, gid: gid_t) -> Self {
+        Self(ucred { pid, uid, gid })
+    }
+
+    /// Credentials of the calling process.
+    pub fn current() -> Self {
+        unsafe { Self::new(libc::getpid(), libc::getuid(), libc::getgid()) }
+    }
+
+    pub fn pid(&self) -> pid_t {
+        self.0.pid
+    }
+
+    pub fn uid(&self) -> uid_t {
+        self.0.uid
+    }
+
+    pub fn gid(&self) -> gid_t {
+        self.0.gid
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ExtendedErr {
+    pub const ORIGIN_NONE:         u8 = SO_EE_ORIGIN_NONE;
+    pub const ORIGIN_LOCAL:        u8 = SO_EE_ORIGIN_LOCAL;
+    pub const ORIGIN_ICMP:         u8 = SO_EE_ORIGIN_ICMP;
+    pub const ORIGIN_ICMP6:        u8 = SO_EE_ORIGIN_ICMP6;
+    pub const ORIGIN_TIMESTAMPING: u8 = SO_EE_ORIGIN_TIMESTAMPING;
+    pub const ORIGIN_ZEROCOPY:     u8 = SO_EE_ORIGIN_ZEROCOPY;
+    pub const ORIGIN_TXTIME:       u8 = SO_EE_ORIGIN_TXTIME;
+
+    pub const CODE_TXTIME_INVALID_PARAM: u8 = SO_EE_CODE_TXTIME_INVALID_PARAM;
+    pub const CODE_TXTIME_MISSED:        u8 = SO_EE_CODE_TXTIME_MISSED;
+
+    unsafe fn read(level: c_int, ptr: *const u8, len: usize) -> Self {
+        let err: sock_extended_err = read(ptr);
+
+        let len  = len.saturating_sub(CMSG_LEN(0) as usize + size_of::<sock_extended_err>());
+        let addr = ptr.add(size_of::<sock_extended_err>());
+
+        let family = match len >= size_of::<libc::sa_family_t>() {
+            true  => read::<libc::sa_family_t>(addr) as c_int,
+            false => libc::AF_UNSPEC,
+        };
+
+        let offender = match family {
+            libc::AF_INET if len >= size_of::<libc::sockaddr_in>() => {
+                let sin: libc::sockaddr_in = read(addr);
+                let ip = Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr));
+                Some(SocketAddr::new(ip.into(), u16::from_be(sin.sin_port)))
+            },
+            libc::AF_INET6 if len >= size_of::<libc::sockaddr_in6>() => {
+                let sin6: libc::sockaddr_in6 = read(addr);
+                let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+                Some(SocketAddr::new(ip.into(), u16::from_be(sin6.sin6_port)))
+            },
+            _ => None,
+        };
+
+        Self { level, err, offender }
+    }
+
+    pub fn errno(&self) -> i32 {
+        self.err.ee_errno as i32
+    }
+
+    pub fn origin(&self) -> u8 {
+        self.err.ee_origin
+    }
+
+    /// ICMP type for `ORIGIN_ICMP` and `ORIGIN_ICMP6` errors.
+    pub fn kind(&self) -> u8 {
+        self.err.ee_type
+    }
+
+    pub fn code(&self) -> u8 {
+        self.err.ee_code
+    }
+
+    /// Origin-specific value, such as the path MTU for fragmentation
+    /// needed errors or the timestamp key with `SOF_TIMESTAMPING_OPT_ID`.
+    pub fn info(&self) -> u32 {
+        self.err.ee_info
+    }
+
+    pub fn data(&self) -> u32 {
+        self.err.ee_data
+    }
+
+    /// Range of `MSG_ZEROCOPY` sends completed by an `ORIGIN_ZEROCOPY` entry.
+    pub fn zerocopy(&self) -> Option<RangeInclusive<u32>> {
+        match self.origin() {
+            Self::ORIGIN_ZEROCOPY => Some(self.info()..=self.data()),
+            _                     => None,
+        }
+    }
+
+    /// Transmit time, in nanoseconds on the `SO_TXTIME` clock, of a packet
+    /// dropped for an invalid time or missed deadline.
+    pub fn txtime(&self) -> Option<u64> {
+        match self.origin() {
+            Self::ORIGIN_TXTIME => Some(((self.data() as u64) << 32) | self.info() as u64),
+            _                   => None,
+        }
+    }
+
+    /// Whether the kernel copied the data of completed zerocopy sends,
+    /// as it does for loopback and devices without scatter-gather.
+    pub fn copied(&self) -> bool {
+        self.origin() == Self::ORIGIN_ZEROCOPY && self.code() == SO_EE_CODE_ZEROCOPY_COPIED
+    }
+
+    /// Node that reported the error, such as the router sending an ICMP
+    /// error.
+    pub fn offender(&self) -> Option<SocketAddr> {
+        self.offender
+    }
+
+    pub fn error(&self) -> io::Error {
+        io::Error::from_raw_os_error(self.errno())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Timestamping {
+    /// Software timestamp, on `CLOCK_REALTIME`.
+    pub fn software(&self) -> Option<Duration> {
+        duration(&self.0.ts[0])
+    }
+
+    /// Raw hardware timestamp from the network interface clock.
+    pub fn hardware(&self) -> Option<Duration> {
+        duration(&self.0.ts[2])
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn duration(ts: &libc::timespec) -> Option<Duration> {
+    match (ts.tv_sec, ts.tv_nsec) {
+        (0, 0) => None,
+        (s, n) => Some(Duration::new(s as u64, n as u32)),
     }
 }
 
@@ -190,6 +625,41 @@ impl<'a> From<Ipv6PktInfo> for CMsg<'a> {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl<'a> From<Ipv4PktInfo> for CMsg<'a> {
+    fn from(info: Ipv4PktInfo) -> Self {
+        Self::Ipv4PktInfo(info)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> From<PacketAuxData> for CMsg<'a> {
+    fn from(aux: PacketAuxData) -> Self {
+        Self::PacketAuxData(aux)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> From<Credentials> for CMsg<'a> {
+    fn from(cred: Credentials) -> Self {
+        Self::ScmCredentials(cred)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> From<ExtendedErr> for CMsg<'a> {
+    fn from(err: ExtendedErr) -> Self {
+        Self::ExtendedErr(err)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> From<Timestamping> for CMsg<'a> {
+    fn from(ts: Timestamping) -> Self {
+        Self::Timestamping(ts)
+    }
+}
+
 impl<'a> From<Raw<'a>> for CMsg<'a> {
     fn from(raw: Raw<'a>) -> Self {
         Self::Raw(raw)
@@ -204,6 +674,57 @@ impl fmt::Debug for Ipv6PktInfo {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl fmt::Debug for Ipv4PktInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addr  = self.addr();
+        let dst   = self.spec_dst();
+        let ifidx = self.ifindex();
+        write!(f, "{{ addr: {}, spec_dst: {}, ifindex: {} }}", addr, dst, ifidx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for PacketAuxData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status  = self.status();
+        let len     = self.packet_len();
+        let snaplen = self.snaplen();
+        let tci     = self.vlan_tci();
+        let tpid    = self.vlan_tpid();
+        write!(f, "{{ status: {:#x}, len: {}, snaplen: {}, vlan_tci: {:?}, vlan_tpid: {:?} }}",
+               status, len, snaplen, tci, tpid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{ pid: {}, uid: {}, gid: {} }}", self.pid(), self.uid(), self.gid())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for ExtendedErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{ errno: {}, origin: {}, type: {}, code: {}, info: {}, data: {}, offender: {:?} }}",
+               self.errno(), self.origin(), self.kind(), self.code(), self.info(), self.data(), self.offender)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for Timestamping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{ software: {:?}, hardware: {:?} }}", self.software(), self.hardware())
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
 impl std::error::Error for Error {}
 
 impl fmt::Display for Error {
@@ -0,0 +1,40 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::net::SocketAddr;
+use socket2::SockAddr;
+use crate::addr::FromSockAddr;
+use crate::control::{CMsg, ExtendedErr, Timestamping};
+
+/// Message read from a socket's error queue, such as an ICMP error, a
+/// transmit timestamp, or a zerocopy completion.
+#[derive(Debug)]
+pub struct Message {
+    /// Extended error describing the entry.
+    pub err: Option<ExtendedErr>,
+    /// Timestamps reported with `SO_TIMESTAMPING`.
+    pub timestamps: Option<Timestamping>,
+    /// Destination of the original packet, if known.
+    pub addr: Option<SocketAddr>,
+    /// Original packet, or the part of it that fit.
+    pub data: Vec<u8>,
+}
+
+impl Message {
+    pub fn parse(data: &[u8], ctrl: &[u8], addr: &SockAddr) -> Self {
+        let mut err        = None;
+        let mut timestamps = None;
+
+        for msg in CMsg::decode(ctrl) {
+            match msg {
+                CMsg::ExtendedErr(e)   => err        = Some(e),
+                CMsg::Timestamping(ts) => timestamps = Some(ts),
+                _                      => (),
+            }
+        }
+
+        let addr = SocketAddr::from_sockaddr(addr).ok();
+        let data = data.to_vec();
+
+        Self { err, timestamps, addr, data }
+    }
+}
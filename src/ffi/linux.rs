@@ -2,8 +2,113 @@
 
 use libc::c_int;
 
+pub use libc::in_pktinfo;
+pub use libc::sock_txtime;
+pub use libc::sockaddr_ll;
+pub use libc::sockaddr_nl;
+pub use libc::ucred;
+
+pub use libc::IP_PKTINFO;
+pub use libc::IP_RECVERR;
+pub use libc::IPV6_RECVERR;
+pub use libc::MSG_CMSG_CLOEXEC;
+pub use libc::MSG_ZEROCOPY;
+pub use libc::SO_BINDTODEVICE;
+pub use libc::SO_DOMAIN;
+pub use libc::SO_PASSCRED;
+pub use libc::SO_PROTOCOL;
+pub use libc::SCM_CREDENTIALS;
+
+pub use libc::AF_NETLINK;
+pub use libc::AF_PACKET;
+pub use libc::SOL_NETLINK;
+pub use libc::SOL_PACKET;
+pub use libc::SOL_UDP;
+
+pub use libc::UDP_GRO;
+pub use libc::UDP_SEGMENT;
+
 pub const IPV6_CHECKSUM:     c_int = libc::IPV6_CHECKSUM;
-pub const IPV6_RECVHOPLIMIT: c_int = 51;
-pub const IPV6_HOPLIMIT:     c_int = 52;
-pub const IPV6_RECVPATHMTU:  c_int = 60;
-pub const IPV6_PATHMTU:      c_int = 61;
+pub const IPV6_RECVHOPLIMIT: c_int = libc::IPV6_RECVHOPLIMIT;
+pub const IPV6_HOPLIMIT:     c_int = libc::IPV6_HOPLIMIT;
+pub const IPV6_RECVPATHMTU:  c_int = libc::IPV6_RECVPATHMTU;
+pub const IPV6_PATHMTU:      c_int = libc::IPV6_PATHMTU;
+
+pub const PACKET_AUXDATA:    c_int = 8;
+
+pub const SO_BINDTOIFINDEX:  c_int = 62;
+pub const SO_TIMESTAMPING:   c_int = 37;
+pub const SO_ZEROCOPY:       c_int = 60;
+pub const SO_TXTIME:         c_int = 61;
+pub const SCM_TXTIME:        c_int = SO_TXTIME;
+pub const SCM_TIMESTAMPING:  c_int = SO_TIMESTAMPING;
+
+pub const SOF_TIMESTAMPING_TX_SOFTWARE: u32 = 1 << 1;
+pub const SOF_TIMESTAMPING_SOFTWARE:    u32 = 1 << 4;
+pub const SOF_TIMESTAMPING_OPT_ID:      u32 = 1 << 7;
+pub const SOF_TIMESTAMPING_OPT_TSONLY:  u32 = 1 << 11;
+
+pub use libc::SOF_TXTIME_DEADLINE_MODE;
+pub use libc::SOF_TXTIME_REPORT_ERRORS;
+
+pub const SO_EE_ORIGIN_NONE:         u8 = 0;
+pub const SO_EE_ORIGIN_LOCAL:        u8 = 1;
+pub const SO_EE_ORIGIN_ICMP:         u8 = 2;
+pub const SO_EE_ORIGIN_ICMP6:        u8 = 3;
+pub const SO_EE_ORIGIN_TIMESTAMPING: u8 = 4;
+pub const SO_EE_ORIGIN_ZEROCOPY:     u8 = 5;
+pub const SO_EE_ORIGIN_TXTIME:       u8 = 6;
+
+pub const SO_EE_CODE_ZEROCOPY_COPIED:      u8 = 1;
+pub const SO_EE_CODE_TXTIME_INVALID_PARAM: u8 = 1;
+pub const SO_EE_CODE_TXTIME_MISSED:        u8 = 2;
+
+pub const PACKET_HOST:       u8 = 0;
+pub const PACKET_BROADCAST:  u8 = 1;
+pub const PACKET_MULTICAST:  u8 = 2;
+pub const PACKET_OTHERHOST:  u8 = 3;
+pub const PACKET_OUTGOING:   u8 = 4;
+
+pub const NETLINK_ADD_MEMBERSHIP:  c_int = 1;
+pub const NETLINK_DROP_MEMBERSHIP: c_int = 2;
+pub const NETLINK_EXT_ACK:         c_int = 11;
+pub const NETLINK_GET_STRICT_CHK:  c_int = 12;
+
+pub const TP_STATUS_VLAN_VALID:      u32 = 1 << 4;
+pub const TP_STATUS_VLAN_TPID_VALID: u32 = 1 << 6;
+
+// numbered alike on every architecture since the syscall tables were unified
+pub const SYS_CLOSE_RANGE: libc::c_long = 436;
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Default)]
+#[repr(C)]
+pub struct tpacket_auxdata {
+    pub tp_status:    u32,
+    pub tp_len:       u32,
+    pub tp_snaplen:   u32,
+    pub tp_mac:       u16,
+    pub tp_net:       u16,
+    pub tp_vlan_tci:  u16,
+    pub tp_vlan_tpid: u16,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Default)]
+#[repr(C)]
+pub struct sock_extended_err {
+    pub ee_errno:  u32,
+    pub ee_origin: u8,
+    pub ee_type:   u8,
+    pub ee_code:   u8,
+    pub ee_pad:    u8,
+    pub ee_info:   u32,
+    pub ee_data:   u32,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct scm_timestamping {
+    pub ts: [libc::timespec; 3],
+}
@@ -7,3 +7,6 @@ pub const IPV6_RECVHOPLIMIT: c_int = 37;
 pub const IPV6_HOPLIMIT:     c_int = 47;
 pub const IPV6_RECVPATHMTU:  c_int = 43;
 pub const IPV6_PATHMTU:      c_int = 44;
+
+// received descriptors cannot be made close-on-exec atomically
+pub const MSG_CMSG_CLOEXEC:  c_int = 0;
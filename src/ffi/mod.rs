@@ -22,6 +22,7 @@ pub use libc::IPV6_PKTINFO;
 pub use libc::IPV6_RECVPKTINFO;
 
 pub use libc::SOL_SOCKET;
+pub use libc::SCM_RIGHTS;
 
 pub const IPV6_DONTFRAG: c_int = 62;
 
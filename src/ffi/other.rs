@@ -7,3 +7,6 @@ pub const IPV6_RECVHOPLIMIT: c_int = libc::IPV6_RECVHOPLIMIT;
 pub const IPV6_HOPLIMIT:     c_int = libc::IPV6_HOPLIMIT;
 pub const IPV6_RECVPATHMTU:  c_int = libc::IPV6_RECVPATHMTU;
 pub const IPV6_PATHMTU:      c_int = libc::IPV6_PATHMTU;
+
+// received descriptors cannot be made close-on-exec atomically
+pub const MSG_CMSG_CLOEXEC:  c_int = 0;
@@ -0,0 +1,125 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use libc::{c_int, gid_t};
+use crate::privilege::CAP_NET_RAW;
+use crate::{Domain, Protocol, RawSocket, Type};
+
+/// Kind of ICMP socket obtained by `open`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// `SOCK_RAW` socket, which requires `CAP_NET_RAW`.
+    Raw,
+    /// Unprivileged `SOCK_DGRAM` "ping" socket, which requires a group in
+    /// `net.ipv4.ping_group_range`. The kernel manages ICMP identifiers
+    /// and checksums, and received packets have no IP header.
+    Dgram,
+}
+
+/// Explains why neither kind of ICMP socket could be opened.
+#[derive(Debug)]
+pub struct Error {
+    /// Error opening a raw socket.
+    pub raw: io::Error,
+    /// Error opening a datagram socket.
+    pub dgram: io::Error,
+    /// Whether the calling thread has `CAP_NET_RAW` in its effective set,
+    /// if that could be determined.
+    pub cap_net_raw: Option<bool>,
+    /// Allowed range from `net.ipv4.ping_group_range`, if readable.
+    pub ping_group_range: Option<(gid_t, gid_t)>,
+}
+
+const PING_GROUP_RANGE: &str = "/proc/sys/net/ipv4/ping_group_range";
+
+/// Open an ICMP or ICMPv6 socket for `domain`, preferring a raw socket and
+/// falling back to an unprivileged datagram socket. Capabilities and the
+/// ping group range are only probed to explain a failure.
+pub fn open(domain: Domain) -> Result<(RawSocket, Mode), Error> {
+    let protocol = match c_int::from(domain) {
+        libc::AF_INET6 => Protocol::icmpv6(),
+        _              => Protocol::icmpv4(),
+    };
+
+    let raw = match RawSocket::new(domain, Type::raw(), Some(protocol)) {
+        Ok(sock) => return Ok((sock, Mode::Raw)),
+        Err(e)   => e,
+    };
+
+    let dgram = match RawSocket::new(domain, Type::dgram(), Some(protocol)) {
+        Ok(sock) => return Ok((sock, Mode::Dgram)),
+        Err(e)   => e,
+    };
+
+    Err(Error {
+        raw,
+        dgram,
+        cap_net_raw:      has_cap_net_raw().ok(),
+        ping_group_range: ping_group_range().ok(),
+    })
+}
+
+/// Whether the calling thread has `CAP_NET_RAW` in its effective set.
+pub fn has_cap_net_raw() -> io::Result<bool> {
+    let status = fs::read_to_string("/proc/thread-self/status")?;
+
+    let caps = status.lines().find_map(|line| line.strip_prefix("CapEff:"));
+    let caps = caps.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing CapEff"))?;
+    let caps = u64::from_str_radix(caps.trim(), 16).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(caps & (1 << CAP_NET_RAW) != 0)
+}
+
+/// Inclusive range of groups allowed to open ICMP datagram sockets.
+pub fn ping_group_range() -> io::Result<(gid_t, gid_t)> {
+    let range = fs::read_to_string(PING_GROUP_RANGE)?;
+
+    let mut bounds = range.split_whitespace().map(str::parse::<gid_t>);
+    match (bounds.next(), bounds.next()) {
+        (Some(Ok(lo)), Some(Ok(hi))) => Ok((lo, hi)),
+        _                            => Err(io::Error::new(io::ErrorKind::InvalidData, range)),
+    }
+}
+
+fn in_groups((lo, hi): (gid_t, gid_t)) -> io::Result<bool> {
+    let mut groups = vec![0; 256];
+
+    let n = unsafe { libc::getgroups(groups.len() as c_int, groups.as_mut_ptr()) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    groups.truncate(n as usize);
+    groups.push(unsafe { libc::getegid() });
+
+    Ok(groups.iter().any(|gid| (lo..=hi).contains(gid)))
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot open ICMP socket: raw socket failed ({})", self.raw)?;
+
+        if self.cap_net_raw == Some(false) {
+            write!(f, " without CAP_NET_RAW")?;
+        }
+
+        write!(f, ", datagram socket failed ({})", self.dgram)?;
+
+        match self.ping_group_range {
+            Some((lo, hi)) if !in_groups((lo, hi)).unwrap_or(true) => {
+                write!(f, " with no group in ping_group_range {}-{}", lo, hi)
+            }
+            Some(..) => Ok(()),
+            None     => write!(f, " and {} is unavailable", PING_GROUP_RANGE),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(io::ErrorKind::PermissionDenied, err)
+    }
+}
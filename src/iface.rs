@@ -0,0 +1,191 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::collections::BTreeSet;
+use std::ffi::{CStr, CString};
+use std::io::{Error, ErrorKind, Result};
+use std::ptr;
+use libc::c_char;
+use crate::addr::MacAddr;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Interface {
+    name:  String,
+    index: u32,
+    flags: u32,
+    mtu:   Option<u32>,
+    mac:   Option<MacAddr>,
+}
+
+impl Interface {
+    pub const IFF_UP:        u32 = libc::IFF_UP as u32;
+    pub const IFF_BROADCAST: u32 = libc::IFF_BROADCAST as u32;
+    pub const IFF_LOOPBACK:  u32 = libc::IFF_LOOPBACK as u32;
+    pub const IFF_RUNNING:   u32 = libc::IFF_RUNNING as u32;
+    pub const IFF_MULTICAST: u32 = libc::IFF_MULTICAST as u32;
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        let cname = CString::new(name).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+        let index = unsafe { libc::if_nametoindex(cname.as_ptr()) };
+        if index == 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let (flags, mtu, mac) = sys::query(&cname)?;
+        let name = name.to_string();
+
+        Ok(Self { name, index, flags, mtu, mac })
+    }
+
+    pub fn from_index(index: u32) -> Result<Self> {
+        let mut buf = [0 as c_char; libc::IF_NAMESIZE];
+
+        let name = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+        if name.is_null() {
+            return Err(Error::last_os_error());
+        }
+
+        let name = unsafe { CStr::from_ptr(name) };
+        let name = name.to_str().map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        Self::from_name(name)
+    }
+
+    /// All interfaces on the system, ordered by index.
+    pub fn all() -> Result<Vec<Self>> {
+        let mut names = BTreeSet::new();
+
+        unsafe {
+            let mut addrs = ptr::null_mut();
+            if libc::getifaddrs(&mut addrs) != 0 {
+                return Err(Error::last_os_error());
+            }
+
+            let mut next = addrs;
+            while let Some(ifa) = next.as_ref() {
+                names.insert(CStr::from_ptr(ifa.ifa_name).to_string_lossy().into_owned());
+                next = ifa.ifa_next;
+            }
+
+            libc::freeifaddrs(addrs);
+        }
+
+        let mut all = names.iter().map(|name| Self::from_name(name)).collect::<Result<Vec<_>>>()?;
+        all.sort_by_key(|iface| iface.index);
+
+        Ok(all)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn mtu(&self) -> Option<u32> {
+        self.mtu
+    }
+
+    pub fn mac(&self) -> Option<MacAddr> {
+        self.mac
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.flags & Self::IFF_UP != 0
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.flags & Self::IFF_LOOPBACK != 0
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::CStr;
+    use std::io::{Error, Result};
+    use std::mem::zeroed;
+    use std::os::unix::io::AsRawFd;
+    use libc::{c_ulong, ifreq};
+    use socket2::{Domain, Socket, Type};
+    use crate::addr::MacAddr;
+
+    pub fn query(name: &CStr) -> Result<(u32, Option<u32>, Option<MacAddr>)> {
+        let sock = Socket::new(Domain::ipv4(), Type::dgram(), None)?;
+
+        let flags = ioctl(&sock, name, libc::SIOCGIFFLAGS)?;
+        let mtu   = ioctl(&sock, name, libc::SIOCGIFMTU)?;
+        let hw    = ioctl(&sock, name, libc::SIOCGIFHWADDR)?;
+
+        unsafe {
+            let flags = flags.ifr_ifru.ifru_flags as u16 as u32;
+            let mtu   = mtu.ifr_ifru.ifru_mtu as u32;
+            let hw    = hw.ifr_ifru.ifru_hwaddr;
+
+            let mac = match hw.sa_family {
+                libc::ARPHRD_ETHER => {
+                    let mut octets = [0u8; 6];
+                    octets.iter_mut().zip(&hw.sa_data).for_each(|(o, b)| *o = *b as u8);
+                    Some(MacAddr::from(octets))
+                },
+                _ => None,
+            };
+
+            Ok((flags, Some(mtu), mac))
+        }
+    }
+
+    fn ioctl(sock: &Socket, name: &CStr, request: c_ulong) -> Result<ifreq> {
+        unsafe {
+            let mut req: ifreq = zeroed();
+
+            let name = name.to_bytes();
+            if name.len() >= req.ifr_name.len() {
+                return Err(Error::from_raw_os_error(libc::ENODEV));
+            }
+            req.ifr_name.iter_mut().zip(name).for_each(|(c, b)| *c = *b as _);
+
+            if libc::ioctl(sock.as_raw_fd(), request as _, &mut req) != 0 {
+                return Err(Error::last_os_error());
+            }
+
+            Ok(req)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::ffi::CStr;
+    use std::io::{Error, Result};
+    use std::ptr;
+    use crate::addr::MacAddr;
+
+    pub fn query(name: &CStr) -> Result<(u32, Option<u32>, Option<MacAddr>)> {
+        let mut flags = 0;
+
+        unsafe {
+            let mut addrs = ptr::null_mut();
+            if libc::getifaddrs(&mut addrs) != 0 {
+                return Err(Error::last_os_error());
+            }
+
+            let mut next = addrs;
+            while let Some(ifa) = next.as_ref() {
+                if CStr::from_ptr(ifa.ifa_name) == name {
+                    flags = ifa.ifa_flags as u32;
+                }
+                next = ifa.ifa_next;
+            }
+
+            libc::freeifaddrs(addrs);
+        }
+
+        Ok((flags, None, None))
+    }
+}
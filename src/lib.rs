@@ -6,26 +6,130 @@ pub use socket2::Domain;
 pub use socket2::Type;
 pub use socket2::Protocol;
 
+pub mod addr;
+#[cfg(target_os = "linux")]
+pub mod activation;
+#[cfg(feature = "async-io")]
+pub mod async_io;
+#[cfg(target_os = "linux")]
+pub mod broker;
 pub mod control;
+#[cfg(target_os = "linux")]
+pub mod errqueue;
 pub mod ffi;
+#[cfg(target_os = "linux")]
+pub mod icmp;
+pub mod iface;
+#[cfg(target_os = "linux")]
+pub mod netlink;
+#[cfg(target_os = "linux")]
+pub mod netns;
+#[cfg(target_os = "linux")]
+pub mod privilege;
 pub mod option;
+pub mod packet;
 pub mod prelude;
 
 mod socket;
 
 #[cfg(feature = "async-tokio")]
 pub mod tokio;
+#[cfg(target_os = "linux")]
+pub mod udp;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+#[cfg(target_os = "linux")]
+pub mod zerocopy;
 
 #[cfg(test)]
 mod test {
     use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Result};
     use std::net::{IpAddr, SocketAddr};
-    use std::thread::sleep;
+    use std::process::Command;
+    use std::thread::{self, sleep};
     use std::time::Duration;
     use libc::{c_int, SOCK_DGRAM, SOCK_STREAM};
-    use crate::{RawSocket, Domain, Type};
+    use crate::{RawSocket, Domain, Protocol, Type};
     use crate::option::{Level, Name};
 
+    /// Run `f` on a new thread inside a fresh network namespace, after
+    /// applying `ip` commands to set it up.
+    pub(crate) fn netns<F, R>(setup: &[&str], f: F) -> Result<R>
+    where
+        F: FnOnce() -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let setup = setup.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        thread::spawn(move || {
+            if unsafe { libc::unshare(libc::CLONE_NEWNET) } != 0 {
+                return Err(Error::last_os_error());
+            }
+
+            for cmd in &setup {
+                let status = Command::new("ip").args(cmd.split_whitespace()).status()?;
+                if !status.success() {
+                    return Err(Error::other(format!("ip {}: {}", cmd, status)));
+                }
+            }
+
+            f()
+        }).join().expect("netns thread")
+    }
+
+    #[cfg(feature = "async-tokio")]
+    pub(crate) fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        let rt = ::tokio::runtime::Builder::new_current_thread().enable_all().build();
+        rt.expect("runtime").block_on(f)
+    }
+
+    pub(crate) fn ifindex(name: &str) -> Result<u32> {
+        let name = std::ffi::CString::new(name)?;
+        match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+            0 => Err(Error::last_os_error()),
+            n => Ok(n),
+        }
+    }
+
+    pub(crate) fn retry<F: FnMut() -> Result<R>, R>(mut f: F) -> Result<R> {
+        let is_wb = |e: &Error| e.kind() == ErrorKind::WouldBlock;
+
+        for _ in 0..50 {
+            match f() {
+                Err(ref e) if is_wb(e) => sleep(Duration::from_millis(20)),
+                result                 => return result,
+            }
+        }
+
+        Err(Error::from(ErrorKind::TimedOut))
+    }
+
+    /// Run `f` in a forked child, for tests that change process-wide
+    /// state, and return its exit status.
+    pub(crate) fn fork(f: fn() -> Result<()>) -> Result<c_int> {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        match unsafe { libc::fork() } {
+            -1  => Err(Error::last_os_error()),
+            0   => unsafe {
+                let code = match catch_unwind(AssertUnwindSafe(f)) {
+                    Ok(Ok(())) => 0,
+                    Ok(Err(e)) => {
+                        eprintln!("forked test failed: {}", e);
+                        1
+                    },
+                    Err(_)     => 2,
+                };
+                libc::_exit(code)
+            },
+            pid => {
+                let mut status = 0;
+                unsafe { libc::waitpid(pid, &mut status, 0) };
+                Ok(libc::WEXITSTATUS(status))
+            },
+        }
+    }
+
     #[test]
     fn get_sockopt() -> Result<()> {
         let ipv4  = Domain::ipv4();
@@ -66,12 +170,12 @@ mod test {
         let send = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
         let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
 
-        send.bind(&addr)?;
-        recv.bind(&addr)?;
+        send.bind(addr)?;
+        recv.bind(addr)?;
 
         let sent = [0u8; 64];
         let addr = recv.local_addr()?;
-        let n = send.send_msg(&addr, &[IoSlice::new(&sent)], &[])?;
+        let n = send.send_msg(addr, &[IoSlice::new(&sent)], &[])?;
 
         assert_eq!(n, sent.len());
 
@@ -96,4 +200,1721 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn packet_auxdata_vlan() -> Result<()> {
+        use crate::addr::LinkAddr;
+        use crate::control::CMsg;
+
+        const ETH_P_ALL:   u16 = libc::ETH_P_ALL as u16;
+        const ETH_P_8021Q: u16 = libc::ETH_P_8021Q as u16;
+        const ETH_P_LOCAL: u16 = 0x88b5;
+
+        let setup = &[
+            "link add va type veth peer name vb",
+            "link set va up",
+            "link set vb up",
+        ];
+
+        netns(setup, || {
+            let packet = Domain::from(libc::AF_PACKET);
+            let enable: c_int = 1;
+
+            let send = RawSocket::new(packet, Type::raw(), None)?;
+            let recv = RawSocket::new(packet, Type::raw(), None)?;
+            recv.bind(LinkAddr::new(ETH_P_ALL, ifindex("vb")?))?;
+            recv.set_sockopt(Level::PACKET, Name::PACKET_AUXDATA, &enable)?;
+            recv.set_nonblocking(true)?;
+
+            let mut frame = [0u8; 64];
+            frame[0..6].copy_from_slice(&[0xff; 6]);
+            frame[6..12].copy_from_slice(&[0x02, 0, 0, 0, 0, 1]);
+            frame[12..14].copy_from_slice(&ETH_P_LOCAL.to_be_bytes());
+
+            let mut tagged = [0u8; 68];
+            tagged[..12].copy_from_slice(&frame[..12]);
+            tagged[12..16].copy_from_slice(&[0x81, 0x00, 0x00, 0x0a]);
+            tagged[16..].copy_from_slice(&frame[12..]);
+
+            let dst = LinkAddr::new(ETH_P_8021Q, ifindex("va")?);
+            send.send_to(&tagged[..], dst)?;
+
+            let mut data = [0u8; 128];
+            let mut ctrl = [0u8; 64];
+
+            let (n, from) = loop {
+                let iovec = &[IoSliceMut::new(&mut data)];
+                let (n, from) = retry(|| recv.recv_msg_as::<LinkAddr>(iovec, &mut ctrl))?;
+                if from.protocol() == ETH_P_LOCAL {
+                    break (n, from);
+                }
+            };
+
+            assert_eq!(from.ifindex(), ifindex("vb")?);
+            assert_eq!(&data[..n], &frame[..]);
+
+            let aux = CMsg::decode(&ctrl).find_map(|msg| match msg {
+                CMsg::PacketAuxData(aux) => Some(aux),
+                _                        => None,
+            }).expect("PACKET_AUXDATA");
+
+            assert_eq!(aux.vlan_tci(),  Some(10));
+            assert_eq!(aux.vlan_tpid(), Some(0x8100));
+
+            let mut buf = [0u8; 128];
+            let restored = aux.restore_vlan(&data[..n], &mut buf).expect("restore");
+
+            assert_eq!(restored, &tagged[..]);
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn ethernet_codec() {
+        use crate::addr::MacAddr;
+        use crate::packet::{Ethernet, Vlan, VlanTag};
+
+        let dst = MacAddr::BROADCAST;
+        let src = "02:00:00:00:00:01".parse::<MacAddr>().unwrap();
+        let tag = Vlan::QinQ(VlanTag::dot1ad(100), VlanTag::dot1q(10).with_pcp(5));
+        let eth = Ethernet::new(dst, src, Ethernet::IPV4, b"payload").with_vlan(tag);
+
+        let mut buf = [0u8; 64];
+        let pkt = eth.encode(&mut buf).unwrap();
+
+        assert_eq!(pkt.len(), Ethernet::HEADER_SIZE + 8 + 7);
+        assert_eq!(&pkt[12..20], &[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0xa0, 0x0a]);
+        assert_eq!(Ethernet::decode(pkt), Ok(eth));
+        assert_eq!(src.to_string(), "02:00:00:00:00:01");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn arp_request_reply() -> Result<()> {
+        use std::net::Ipv4Addr;
+        use crate::addr::{LinkAddr, MacAddr};
+        use crate::packet::{Arp, Ethernet};
+
+        let setup = &[
+            "link add va address 02:00:00:00:00:0a type veth peer name vb",
+            "addr add 10.0.0.2/24 dev vb",
+            "link set va up",
+            "link set vb up",
+        ];
+
+        netns(setup, || {
+            let packet = Domain::from(libc::AF_PACKET);
+            let sock   = RawSocket::new(packet, Type::dgram(), None)?;
+            let va     = ifindex("va")?;
+            sock.bind(LinkAddr::new(Ethernet::ARP, va))?;
+            sock.set_nonblocking(true)?;
+
+            let sha = MacAddr::new(0x02, 0, 0, 0, 0, 0x0a);
+            let spa = Ipv4Addr::new(10, 0, 0, 1);
+            let tpa = Ipv4Addr::new(10, 0, 0, 2);
+            let req = Arp::request(sha, spa, tpa);
+
+            let mut buf = [0u8; Arp::SIZE];
+            let pkt = req.encode(&mut buf).expect("encode");
+            let dst = LinkAddr::new(Ethernet::ARP, va).with_mac(req.dst());
+            sock.send_to(pkt, dst)?;
+
+            let mut buf = [0u8; 64];
+            let reply = loop {
+                let (n, from) = retry(|| sock.recv_from_as::<LinkAddr>(&mut buf))?;
+                match Arp::decode(&buf[..n]) {
+                    Ok(arp) if arp.is_reply() => break (arp, from),
+                    _                         => continue,
+                }
+            };
+
+            let (arp, from) = reply;
+            assert_eq!(arp.spa, tpa);
+            assert_eq!(arp.tpa, spa);
+            assert_eq!(arp.tha, sha);
+            assert_eq!(from.mac(), Some(arp.sha));
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "async-tokio"))]
+    fn arp_scan_respond() -> Result<()> {
+        use std::net::Ipv4Addr;
+        use crate::addr::MacAddr;
+        use crate::tokio::arp::{ArpResponder, ArpScanner};
+
+        let setup = &[
+            "link add va address 02:00:00:00:00:0a type veth peer name vb address 02:00:00:00:00:0b",
+            "addr add 10.0.0.2/24 dev vb",
+            "link set va up",
+            "link set vb up",
+        ];
+
+        netns(setup, || block_on(async {
+            let timeout = Duration::from_millis(200);
+
+            let va  = ifindex("va")?;
+            let vb  = ifindex("vb")?;
+            let mac = MacAddr::new(0x02, 0, 0, 0, 0, 0x0a);
+            let vip = Ipv4Addr::new(10, 0, 0, 100);
+            let vmac = MacAddr::new(0x02, 0, 0, 0, 0, 0x64);
+
+            let scanner = ArpScanner::new(va, mac, Ipv4Addr::new(10, 0, 0, 1))?.with_timeout(timeout);
+            let replies = scanner.scan(Ipv4Addr::new(10, 0, 0, 0), 29).await?;
+
+            let invalid = scanner.scan(Ipv4Addr::new(10, 0, 0, 0), 8).await.unwrap_err();
+            assert_eq!(invalid.kind(), ErrorKind::InvalidInput);
+
+            assert_eq!(replies.len(), 1);
+            assert_eq!(replies[0].ip,  Ipv4Addr::new(10, 0, 0, 2));
+            assert_eq!(replies[0].mac, MacAddr::new(0x02, 0, 0, 0, 0, 0x0b));
+
+            let responder = ArpResponder::new(va)?.with(vip, vmac);
+            let responder = ::tokio::spawn(async move { responder.respond().await });
+
+            let scanner = ArpScanner::new(vb, MacAddr::new(0x02, 0, 0, 0, 0, 0x0b), Ipv4Addr::new(10, 0, 0, 2))?;
+            let replies = scanner.with_timeout(timeout).scan(vip, 32).await?;
+
+            assert_eq!(replies.len(), 1);
+            assert_eq!(replies[0].mac, vmac);
+            assert_eq!(responder.await?.map(|arp| arp.sha)?, vmac);
+
+            Ok(())
+        }))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn ndp_codec() {
+        use std::net::Ipv6Addr;
+        use crate::addr::MacAddr;
+        use crate::packet::ndp::{PrefixInfo, RouterAdvert};
+        use crate::packet::{Ndp, NdpOption};
+
+        let mac    = MacAddr::new(0x02, 0, 0, 0, 0, 0x0a);
+        let prefix = PrefixInfo::new("2001:db8:1::".parse().unwrap(), 64);
+        let dns    = "2001:db8::53".parse::<Ipv6Addr>().unwrap().octets();
+        let opts   = [
+            NdpOption::SourceLinkAddr(mac),
+            NdpOption::Mtu(1400),
+            NdpOption::PrefixInfo(prefix),
+            NdpOption::Rdnss(600, &[dns]),
+        ];
+
+        let mut buf = [0u8; 128];
+        let opts = NdpOption::encode(&mut buf, &opts).unwrap();
+        assert_eq!(opts.len(), 8 + 8 + 32 + 24);
+
+        let ra = Ndp::RouterAdvert(RouterAdvert { managed: true, ..RouterAdvert::new(1800, opts) });
+
+        let mut buf = [0u8; 128];
+        let pkt = ra.encode(&mut buf).unwrap();
+        assert_eq!(&pkt[..8], &[134, 0, 0, 0, 64, 0x80, 0x07, 0x08]);
+        assert_eq!(Ndp::decode(pkt), Ok(ra));
+
+        let decoded = NdpOption::decode(ra.options()).collect::<Vec<_>>();
+        assert_eq!(decoded[0], NdpOption::SourceLinkAddr(mac));
+        assert_eq!(decoded[1], NdpOption::Mtu(1400));
+        assert_eq!(decoded[2], NdpOption::PrefixInfo(prefix));
+        assert_eq!(decoded[3], NdpOption::Rdnss(600, &[dns]));
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "async-tokio"))]
+    fn ndp_probe_advertise() -> Result<()> {
+        use crate::addr::MacAddr;
+        use crate::packet::ndp::{self, PrefixInfo, RouterAdvert, RouterSolicit};
+        use crate::packet::{Ndp, NdpOption};
+        use crate::tokio::ndp::{NdpSocket, RouterAdvertiser};
+
+        let setup = &[
+            "link add va address 02:00:00:00:00:0a type veth peer name vb address 02:00:00:00:00:0b",
+            "link set va addrgenmode none",
+            "link set vb addrgenmode none",
+            "addr add fe80::a/64 dev va nodad",
+            "addr add fe80::b/64 dev vb nodad",
+            "link set va up",
+            "link set vb up",
+        ];
+
+        netns(setup, || block_on(async {
+            let timeout = Duration::from_secs(2);
+
+            let va = NdpSocket::new(ifindex("va")?)?;
+            let vb = NdpSocket::new(ifindex("vb")?)?;
+
+            let mac = MacAddr::new(0x02, 0, 0, 0, 0, 0x0a);
+            let neighbor = va.probe("fe80::b".parse().unwrap(), mac, timeout).await?;
+            let neighbor = neighbor.expect("neighbor advertisement");
+            assert_eq!(neighbor.mac, Some(MacAddr::new(0x02, 0, 0, 0, 0, 0x0b)));
+            assert!(!neighbor.router);
+
+            let prefix = PrefixInfo::new("2001:db8:1::".parse().unwrap(), 64);
+            let mut opts = [0u8; 32];
+            let opts = NdpOption::encode(&mut opts, &[NdpOption::PrefixInfo(prefix)])?;
+
+            let mut ra = [0u8; 64];
+            let ra = Ndp::RouterAdvert(RouterAdvert::new(1800, opts)).encode(&mut ra)?.to_vec();
+
+            let advertiser = RouterAdvertiser::new(ifindex("va")?)?.with_interval(Duration::from_secs(3600));
+            let advertiser = ::tokio::spawn(async move {
+                let ra = match Ndp::decode(&ra) {
+                    Ok(Ndp::RouterAdvert(ra)) => ra,
+                    _                         => unreachable!(),
+                };
+                advertiser.run(&ra).await
+            });
+
+            let rs = Ndp::RouterSolicit(RouterSolicit::new(&[]));
+            vb.send(ndp::ALL_ROUTERS, &rs).await?;
+
+            let mut buf = [0u8; 1280];
+            loop {
+                if let (Ndp::RouterAdvert(ra), from) = vb.recv(&mut buf).await? {
+                    let prefix = NdpOption::PrefixInfo(prefix);
+                    assert_eq!(from, "fe80::a".parse::<std::net::Ipv6Addr>().unwrap());
+                    assert_eq!(ra.router_lifetime, 1800);
+                    assert_eq!(NdpOption::decode(ra.options).next(), Some(prefix));
+                    break;
+                }
+            }
+
+            let first = ::tokio::time::Instant::now();
+            vb.send(ndp::ALL_ROUTERS, &rs).await?;
+            vb.send(ndp::ALL_ROUTERS, &rs).await?;
+
+            let mut ras = 0;
+            let window = first + ndp::MIN_DELAY_BETWEEN_RAS + ndp::MAX_RA_DELAY_TIME * 2;
+            while let Ok(result) = ::tokio::time::timeout_at(window, vb.recv(&mut buf)).await {
+                if let Ndp::RouterAdvert(..) = result?.0 {
+                    assert!(first.elapsed() >= ndp::MIN_DELAY_BETWEEN_RAS - Duration::from_millis(100));
+                    ras += 1;
+                }
+            }
+            assert_eq!(ras, 1);
+
+            advertiser.abort();
+
+            Ok(())
+        }))?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn netlink_request() -> Result<()> {
+        use crate::addr::NetlinkAddr;
+        use crate::netlink::{self, NlMsg, Nla, NLM_F_ACK, NLM_F_DUMP, NETLINK_ROUTE};
+
+        const RTM_NEWLINK:  u16 = 16;
+        const RTM_GETLINK:  u16 = 18;
+        const IFLA_IFNAME:  u16 = 3;
+        const RTNLGRP_LINK: c_int = 1;
+
+        netns(&[], || {
+            let sock = netlink::socket(NETLINK_ROUTE)?;
+            sock.bind(NetlinkAddr::new(0, 0))?;
+            assert_ne!(sock.local_addr_as::<NetlinkAddr>()?.pid(), 0);
+
+            let ifinfo = [0u8; 16];
+            let msg    = NlMsg::new(RTM_GETLINK, NLM_F_DUMP, 1, &ifinfo);
+
+            let mut names = Vec::new();
+            netlink::request(&sock, &msg, |msg| {
+                assert_eq!(msg.kind, RTM_NEWLINK);
+                let attrs = Nla::decode(&msg.payload[16..]);
+                names.extend(attrs.filter(|a| a.id() == IFLA_IFNAME).filter_map(|a| a.str()).map(String::from));
+                Ok(())
+            })?;
+            assert_eq!(names, ["lo"]);
+
+            let mut ifinfo = [0u8; 16];
+            ifinfo[4..8].copy_from_slice(&999i32.to_ne_bytes());
+            let msg = NlMsg::new(RTM_GETLINK, NLM_F_ACK, 2, &ifinfo);
+            let err = netlink::request(&sock, &msg, |_| Ok(())).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::ENODEV));
+
+            let mut buf = [0u8; 64];
+            let stale = NlMsg::new(RTM_GETLINK, NLM_F_ACK, 3, &ifinfo).encode(&mut buf)?.to_vec();
+            sock.send_to(&stale, NetlinkAddr::kernel())?;
+            let msg = NlMsg::new(RTM_GETLINK, NLM_F_DUMP, 4, &ifinfo);
+            let mut replies = 0;
+            netlink::request(&sock, &msg, |_| {
+                replies += 1;
+                Ok(())
+            })?;
+            assert_eq!(replies, 1);
+
+            let group = netlink::socket(NETLINK_ROUTE)?;
+            group.bind(NetlinkAddr::new(0, 0))?;
+            group.set_sockopt(Level::NETLINK, Name::NETLINK_ADD_MEMBERSHIP, &RTNLGRP_LINK)?;
+
+            let status = Command::new("ip").args(["link", "add", "va", "type", "veth", "peer", "name", "vb"]).status()?;
+            assert!(status.success());
+
+            let mut buf = [0u8; 8192];
+            let (n, from) = group.recv_from_as::<NetlinkAddr>(&mut buf)?;
+            let msg = NlMsg::decode(&buf[..n]).next().expect("message")?;
+            assert_eq!(msg.kind, RTM_NEWLINK);
+            assert_eq!(from.groups(), 1 << (RTNLGRP_LINK - 1));
+
+            let mut attrs = [0u8; 16];
+            let attrs = Nla::encode(&mut attrs, &[Nla::new(IFLA_IFNAME, b"lo\0")])?;
+            assert_eq!(attrs, &[7, 0, 3, 0, b'l', b'o', 0, 0]);
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "async-tokio"))]
+    fn netlink_request_async() -> Result<()> {
+        use crate::netlink::{NlMsg, NLM_F_DUMP, NETLINK_ROUTE};
+        use crate::tokio::netlink;
+
+        netns(&[], || block_on(async {
+            let sock   = netlink::socket(NETLINK_ROUTE)?;
+            let ifinfo = [0u8; 16];
+            let msg    = NlMsg::new(18, NLM_F_DUMP, 1, &ifinfo);
+
+            let mut count = 0;
+            netlink::request(&sock, &msg, |_| {
+                count += 1;
+                Ok(())
+            }).await?;
+            assert_eq!(count, 1);
+
+            Ok(())
+        }))?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn rtnetlink_query() -> Result<()> {
+        use crate::addr::MacAddr;
+        use crate::netlink::route::{Neighbor, Route, Rtnetlink};
+
+        let setup = &[
+            "link add va address 02:00:00:00:00:0a type veth peer name vb",
+            "addr add 10.1.0.1/24 dev va",
+            "neigh add 10.1.0.2 lladdr 02:00:00:00:00:0b dev va nud permanent",
+            "link set va up",
+            "link set vb up",
+        ];
+
+        netns(setup, || {
+            let mut rtnl = Rtnetlink::new()?;
+            let va   = ifindex("va")?;
+
+            let links = rtnl.links()?;
+            let link  = links.iter().find(|link| link.name == "va").expect("va");
+            assert_eq!(link.index, va);
+            assert_eq!(link.mac, Some(MacAddr::new(0x02, 0, 0, 0, 0, 0x0a)));
+            assert!(link.is_up());
+            assert!(links.iter().any(|link| link.name == "lo" && link.mtu == Some(65536)));
+
+            let addrs = rtnl.addresses()?;
+            let addr  = addrs.iter().find(|addr| addr.index == va).expect("va address");
+            assert_eq!(addr.addr, "10.1.0.1".parse::<IpAddr>().unwrap());
+            assert_eq!(addr.prefix_len, 24);
+            assert_eq!(addr.label.as_deref(), Some("va"));
+
+            let routes = rtnl.routes()?;
+            assert!(routes.iter().any(|route| {
+                route.table == Route::RT_TABLE_MAIN && route.oif == Some(va) && route.dst_len == 24
+            }));
+
+            let neighbors = rtnl.neighbors()?;
+            let neighbor  = neighbors.iter().find(|n| n.index == va && n.addr.is_ipv4()).expect("neighbor");
+            assert_eq!(neighbor.addr, "10.1.0.2".parse::<IpAddr>().unwrap());
+            assert_eq!(neighbor.mac, Some(MacAddr::new(0x02, 0, 0, 0, 0, 0x0b)));
+            assert_eq!(neighbor.state, Neighbor::NUD_PERMANENT);
+
+            let route = rtnl.route_to("10.1.0.9".parse().unwrap())?;
+            assert_eq!(route.oif, Some(va));
+            assert_eq!(route.src, Some("10.1.0.1".parse().unwrap()));
+
+            let err = rtnl.route_to("192.0.2.1".parse().unwrap()).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::ENETUNREACH));
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "async-tokio"))]
+    fn rtnetlink_watch() -> Result<()> {
+        use futures::StreamExt;
+        use crate::netlink::route::{Event, RTNLGRP_IPV4_IFADDR, RTNLGRP_LINK};
+        use crate::tokio::netlink::{self, Rtnetlink};
+
+        netns(&[], || block_on(async {
+            let mut rtnl = Rtnetlink::new()?;
+            let events = netlink::watch(&[RTNLGRP_LINK, RTNLGRP_IPV4_IFADDR])?;
+            futures::pin_mut!(events);
+
+            let links = rtnl.links().await?;
+            assert_eq!(links.len(), 1);
+
+            for args in &["link add va type veth peer name vb", "link set va up", "addr add 10.1.0.1/24 dev va"] {
+                let status = Command::new("ip").args(args.split_whitespace()).status()?;
+                assert!(status.success());
+            }
+
+            let mut link = false;
+            while let Some(event) = events.next().await {
+                match event? {
+                    Event::NewLink(l) if l.name == "va" => link = true,
+                    Event::NewAddress(addr) => {
+                        assert_eq!(addr.addr, "10.1.0.1".parse::<IpAddr>().unwrap());
+                        break;
+                    },
+                    _ => (),
+                }
+            }
+            assert!(link);
+
+            let route = rtnl.route_to("10.1.0.2".parse().unwrap()).await?;
+            assert_eq!(route.oif, Some(ifindex("va")?));
+
+            Ok(())
+        }))?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn interface_resolution() -> Result<()> {
+        use crate::addr::MacAddr;
+        use crate::control::CMsg;
+        use crate::iface::Interface;
+
+        let setup = &[
+            "link add va address 02:00:00:00:00:0a mtu 1400 type veth peer name vb",
+            "link set lo up",
+            "link set va up",
+        ];
+
+        netns(setup, || {
+            let va = Interface::from_name("va")?;
+            assert_eq!(va.index(), ifindex("va")?);
+            assert_eq!(va.mtu(), Some(1400));
+            assert_eq!(va.mac(), Some(MacAddr::new(0x02, 0, 0, 0, 0, 0x0a)));
+            assert!(va.is_up() && !va.is_loopback());
+            assert_eq!(Interface::from_index(va.index())?, va);
+
+            let lo = Interface::from_name("lo")?;
+            assert!(lo.is_loopback());
+            assert_eq!(lo.mac(), None);
+
+            let all = Interface::all()?;
+            let mut names = all.iter().map(Interface::name).collect::<Vec<_>>();
+            names.sort_unstable();
+            assert_eq!(names, ["lo", "va", "vb"]);
+            assert!(all.windows(2).all(|w| w[0].index() < w[1].index()));
+
+            let err = Interface::from_name("missing").unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::ENODEV));
+
+            let sock = RawSocket::new(Domain::ipv4(), Type::raw(), Some(253.into()))?;
+            sock.bind_device(Some("va"))?;
+            assert_eq!(sock.device()?.as_deref(), Some("va"));
+            sock.bind_interface(&lo)?;
+            assert_eq!(sock.device()?.as_deref(), Some("lo"));
+
+            let enable: c_int = 1;
+            sock.set_sockopt(Level::IPV4, Name::IPV4_PKTINFO, &enable)?;
+            sock.send_to(b"pktinfo", "127.0.0.1:0")?;
+
+            let mut buf  = [0u8; 64];
+            let mut ctrl = [0u8; 128];
+            let iovec = &[IoSliceMut::new(&mut buf)];
+            sock.recv_msg(iovec, &mut ctrl)?;
+
+            let info = CMsg::decode(&ctrl).find_map(|msg| match msg {
+                CMsg::Ipv4PktInfo(info) => Some(info),
+                _                       => None,
+            }).expect("pktinfo");
+            assert_eq!(info.addr(), "127.0.0.1".parse::<std::net::Ipv4Addr>().unwrap());
+            assert_eq!(info.interface()?, lo);
+
+            sock.bind_device(None)?;
+            assert_eq!(sock.device()?, None);
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn ipv6_scope_id() -> Result<()> {
+        use std::net::{Ipv6Addr, SocketAddrV6};
+        use crate::control::{CMsg, Ipv6PktInfo};
+
+        let setup = &[
+            "link add va type veth peer name vb",
+            "link set va addrgenmode none",
+            "link set vb addrgenmode none",
+            "addr add fe80::1/64 dev lo nodad",
+            "addr add fe80::a/64 dev va nodad",
+            "addr add fe80::b/64 dev vb nodad",
+            "link set lo up",
+            "link set va up",
+            "link set vb up",
+        ];
+
+        netns(setup, || {
+            let socket = |device| -> Result<RawSocket> {
+                let sock = RawSocket::new(Domain::ipv6(), Type::raw(), Some(253.into()))?;
+                sock.bind_device(Some(device))?;
+                let enable: c_int = 1;
+                sock.set_sockopt(Level::IPV6, Name::IPV6_RECVPKTINFO, &enable)?;
+                sock.set_nonblocking(true)?;
+                Ok(sock)
+            };
+
+            let recv = |sock: &RawSocket| retry(|| {
+                let mut buf  = [0u8; 64];
+                let mut ctrl = [0u8; 128];
+                let iovec = &[IoSliceMut::new(&mut buf)];
+                match sock.recv_msg(iovec, &mut ctrl)? {
+                    (_, SocketAddr::V6(from)) => Ok(from),
+                    (_, from)                 => panic!("unexpected source {}", from),
+                }
+            });
+
+            let (lo, va, vb) = (ifindex("lo")?, ifindex("va")?, ifindex("vb")?);
+            let addr = |s: &str, scope| SocketAddrV6::new(s.parse().unwrap(), 0, 0, scope);
+
+            let sock = socket("lo")?;
+            sock.send_to(b"lo", addr("fe80::1", lo))?;
+            assert_eq!(recv(&sock)?, addr("fe80::1", lo));
+
+            let a = socket("va")?;
+            let b = socket("vb")?;
+
+            a.send_to(b"ping", addr("fe80::b", va))?;
+            let from = recv(&b)?;
+            assert_eq!(from, addr("fe80::a", vb));
+
+            b.send_to(b"pong", from)?;
+            assert_eq!(recv(&a)?, addr("fe80::b", va));
+
+            let info = Ipv6PktInfo::new(Ipv6Addr::UNSPECIFIED, va);
+            let mut ctrl = [0u8; 128];
+            let ctrl = CMsg::encode(&mut ctrl, &[info.into()])?;
+            a.send_msg(addr("fe80::b", 0), &[IoSlice::new(b"pktinfo")], ctrl)?;
+            assert_eq!(recv(&b)?, addr("fe80::a", vb));
+
+            let c = RawSocket::new(Domain::ipv6(), Type::raw(), Some(253.into()))?;
+            c.send_msg(addr("fe80::b", 0), &[IoSlice::new(b"pktinfo")], ctrl)?;
+            assert_eq!(recv(&b)?, addr("fe80::a", vb));
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn socket_in_netns() -> Result<()> {
+        use std::fs::File;
+        use std::os::unix::io::AsRawFd;
+        use crate::netlink::route::{RTM_GETLINK, Link};
+        use crate::netlink::{self, NlMsg, NLM_F_DUMP, NETLINK_ROUTE};
+
+        let ns = netns(&["link add va type veth peer name vb"], || File::open("/proc/thread-self/ns/net"))?;
+
+        let links = |sock: &RawSocket| -> Result<Vec<String>> {
+            let ifinfo = [0u8; 16];
+            let msg    = NlMsg::new(RTM_GETLINK, NLM_F_DUMP, 1, &ifinfo);
+
+            let mut names = Vec::new();
+            netlink::request(sock, &msg, |msg| {
+                names.extend(Link::parse(&msg).map(|link| link.name));
+                Ok(())
+            })?;
+            names.sort();
+
+            Ok(names)
+        };
+
+        let domain   = Domain::from(libc::AF_NETLINK);
+        let protocol = Some(NETLINK_ROUTE.into());
+
+        let sock = RawSocket::new_in_netns(ns.as_raw_fd(), domain, Type::raw(), protocol)?;
+        assert_eq!(links(&sock)?, ["lo", "va", "vb"]);
+
+        let path = format!("/proc/self/fd/{}", ns.as_raw_fd());
+        let sock = RawSocket::new_in_netns(path.as_str(), domain, Type::raw(), protocol)?;
+        assert_eq!(links(&sock)?, ["lo", "va", "vb"]);
+
+        let sock = RawSocket::new(domain, Type::raw(), protocol)?;
+        assert!(!links(&sock)?.contains(&"va".to_string()));
+
+        let err = RawSocket::new_in_netns("missing", domain, Type::raw(), protocol).err();
+        assert_eq!(err.map(|e| e.kind()), Some(ErrorKind::NotFound));
+
+        #[cfg(feature = "async-tokio")]
+        block_on(async {
+            let sock = crate::tokio::RawSocket::new_in_netns(ns.as_raw_fd(), domain, Type::raw(), protocol)?;
+            let ifinfo = [0u8; 16];
+            let msg    = NlMsg::new(RTM_GETLINK, NLM_F_DUMP, 1, &ifinfo);
+
+            let mut count = 0;
+            crate::tokio::netlink::request(&sock, &msg, |_| {
+                count += 1;
+                Ok(())
+            }).await?;
+            assert_eq!(count, 3);
+
+            Result::Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn broker_passes_sockets() -> Result<()> {
+        use std::fs::File;
+        use std::io::Read;
+        use std::os::unix::io::{AsRawFd, FromRawFd};
+        use socket2::SockAddr;
+        use crate::addr::unnamed;
+        use crate::broker::{self, Broker, Client, Policy};
+        use crate::control::CMsg;
+        use crate::Protocol;
+
+        let policy = Policy::new().allow(Domain::ipv4(), Type::raw(), Some(Protocol::icmpv4()));
+
+        // the broker must not keep the write end of a pipe open
+        let mut pipe = [0; 2];
+        if unsafe { libc::pipe2(pipe.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        let mut client = broker::spawn(policy.clone())?;
+        let (mut rx, tx) = unsafe { (File::from_raw_fd(pipe[0]), File::from_raw_fd(pipe[1])) };
+        drop(tx);
+        assert_eq!(rx.read(&mut [0u8; 1])?, 0);
+
+        let sock = client.open(Domain::ipv4(), Type::raw(), Some(Protocol::icmpv4()))?;
+        assert_eq!(sock.get_sockopt::<c_int>(Level::SOCKET, Name::SO_TYPE)?, libc::SOCK_RAW);
+        sock.send_to(&[8, 0, 0xf7, 0xff, 0, 0, 0, 0], "127.0.0.1:0")?;
+
+        #[cfg(feature = "async-tokio")]
+        block_on(async {
+            let sock = client.open_tokio(Domain::ipv4(), Type::raw(), Some(Protocol::icmpv4()))?;
+            sock.send_to(&[8, 0, 0xf7, 0xff, 0, 0, 0, 0], "127.0.0.1:0").await
+        })?;
+
+        let err = client.open(Domain::ipv4(), Type::dgram(), None).err().expect("denied");
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        drop(client);
+
+        let (a, b) = broker::pair()?;
+        let broker = Broker::new(b, policy.allow_uid(12345))?;
+        let thread = thread::spawn(move || broker.serve());
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) }, 0);
+        let (rx, tx) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+        let mut ctrl = [0u8; 64];
+        let ctrl = CMsg::encode(&mut ctrl, &[CMsg::ScmRights(&[tx.as_raw_fd()])])?;
+        a.send_msg_addr(&unnamed(), &[IoSlice::new(b"bad")], ctrl)?;
+        drop(tx);
+
+        let mut reply = [0u8; 4];
+        a.recv_msg_as::<SockAddr>(&[IoSliceMut::new(&mut reply)], &mut [])?;
+        assert_eq!(c_int::from_ne_bytes(reply), libc::EINVAL);
+        assert_eq!((&rx).read(&mut [0u8; 1])?, 0);
+
+        let mut client = Client::new(a);
+        let err = client.open(Domain::ipv4(), Type::raw(), Some(Protocol::icmpv4())).err().expect("denied");
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        drop(client);
+
+        thread.join().expect("broker thread")?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn scm_rights_credentials() -> Result<()> {
+        use std::fs::File;
+        use std::os::unix::io::AsRawFd;
+        use socket2::SockAddr;
+        use crate::addr::unnamed;
+        use crate::control::{CMsg, Credentials};
+
+        let (a, b) = crate::broker::pair()?;
+        let enable: c_int = 1;
+        b.set_sockopt(Level::SOCKET, Name::SO_PASSCRED, &enable)?;
+
+        let null = File::open("/dev/null")?;
+        let fds  = [null.as_raw_fd(), a.as_raw_fd()];
+
+        let mut ctrl = [0u8; 128];
+        let encoded = CMsg::encode(&mut ctrl, &[CMsg::ScmRights(&fds), Credentials::current().into()])?;
+        a.send_msg_addr(&unnamed(), &[IoSlice::new(b"fds")], encoded)?;
+
+        let mut buf  = [0u8; 16];
+        let mut ctrl = [0u8; 128];
+        let iovec = &[IoSliceMut::new(&mut buf)];
+        let (n, _, received) = b.recv_msg_fds::<SockAddr>(iovec, &mut ctrl)?;
+        assert_eq!(&buf[..n], b"fds");
+
+        for msg in CMsg::decode(&ctrl) {
+            match msg {
+                CMsg::ScmCredentials(cred) => {
+                    assert_eq!(cred.pid(), std::process::id() as i32);
+                    assert_eq!(cred.uid(), unsafe { libc::getuid() });
+                },
+                CMsg::Raw(raw)             => assert_eq!(raw.kind, libc::SCM_RIGHTS),
+                msg                        => panic!("unexpected {:?}", msg),
+            }
+        }
+
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|fd| !fds.contains(&fd.as_raw_fd())));
+        for fd in &received {
+            let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+            assert_eq!(flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+        }
+
+        a.send_msg_addr(&unnamed(), &[IoSlice::new(b"fds")], encoded)?;
+        let iovec = &[IoSliceMut::new(&mut buf)];
+        b.recv_msg_as::<SockAddr>(iovec, &mut ctrl)?;
+        let raw = CMsg::decode(&ctrl).find_map(|msg| match msg {
+            CMsg::Raw(raw) => Some(raw.data.to_vec()),
+            _              => None,
+        }).expect("SCM_RIGHTS");
+        assert!(raw.chunks(4).all(|fd| fd == (-1i32).to_ne_bytes()));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn icmp_fallback() -> Result<()> {
+        use std::fs;
+        use crate::icmp::{self, Mode};
+
+        #[repr(C)]
+        struct CapHeader {
+            version: u32,
+            pid:     c_int,
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone, Default)]
+        struct CapData {
+            effective:   u32,
+            permitted:   u32,
+            inheritable: u32,
+        }
+
+        // Capabilities are per-thread, so drop CAP_NET_RAW on the netns
+        // thread rather than entering a user namespace, which requires a
+        // single-threaded process.
+        let drop_net_raw = || -> Result<()> {
+            let mut header = CapHeader { version: 0x2008_0522, pid: 0 };
+            let mut data   = [CapData::default(); 2];
+            unsafe {
+                if libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) != 0 {
+                    return Err(Error::last_os_error());
+                }
+                data[0].effective &= !(1 << 13);
+                if libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) != 0 {
+                    return Err(Error::last_os_error());
+                }
+            }
+            Ok(())
+        };
+
+        netns(&["link set lo up"], move || {
+            let range = "/proc/sys/net/ipv4/ping_group_range";
+
+            let (_, mode) = RawSocket::new_icmp(Domain::ipv4())?;
+            assert_eq!(mode, Mode::Raw);
+
+            drop_net_raw()?;
+            assert!(!icmp::has_cap_net_raw()?);
+
+            fs::write(range, "0 0")?;
+            assert_eq!(icmp::ping_group_range()?, (0, 0));
+
+            let (sock, mode) = RawSocket::new_icmp(Domain::ipv4())?;
+            assert_eq!(mode, Mode::Dgram);
+            sock.set_nonblocking(true)?;
+            sock.send_to(&[8, 0, 0, 0, 0, 0, 0, 1, b'p', b'i', b'n', b'g'], "127.0.0.1:0")?;
+
+            let mut buf = [0u8; 64];
+            let (n, from) = retry(|| sock.recv_from(&mut buf))?;
+            assert_eq!(from.ip(), "127.0.0.1".parse::<IpAddr>().unwrap());
+            assert_eq!(buf[0], 0);
+            assert_eq!(&buf[8..n], b"ping");
+
+            fs::write(range, "1 0")?;
+
+            let err = icmp::open(Domain::ipv4()).err().expect("no ICMP socket");
+            assert_eq!(err.cap_net_raw, Some(false));
+            assert_eq!(err.raw.raw_os_error(), Some(libc::EPERM));
+            assert_eq!(err.dgram.raw_os_error(), Some(libc::EACCES));
+            assert_eq!(err.ping_group_range, Some((1, 0)));
+
+            let err = RawSocket::new_icmp(Domain::ipv6()).err().expect("no ICMPv6 socket");
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+            assert!(err.get_ref().is_some_and(|e| e.is::<icmp::Error>()));
+            assert!(err.to_string().contains("ping_group_range 1-0"));
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn drop_privileges() -> Result<()> {
+        use crate::icmp;
+        use crate::privilege::{Privileges, CAP_NET_RAW};
+
+        let err = Privileges::new(0, 0).keep(64).expect_err("invalid capability");
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        fn icmp() -> Result<RawSocket> {
+            RawSocket::new(Domain::ipv4(), Type::raw(), Some(Protocol::icmpv4()))
+        }
+
+        assert_eq!(fork(|| {
+            let sock = icmp()?;
+
+            Privileges::new(65534, 65534).apply()?;
+            assert_eq!(unsafe { (libc::getuid(), libc::getgid()) }, (65534, 65534));
+            assert!(!icmp::has_cap_net_raw()?);
+
+            let err = icmp().err().expect("raw socket without CAP_NET_RAW");
+            assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+
+            sock.send_to(&[8, 0, 0xf7, 0xfe, 0, 0, 0, 1], "127.0.0.1:0")?;
+
+            Ok(())
+        })?, 0);
+
+        assert_eq!(fork(|| {
+            Privileges::new(65534, 65534).keep(CAP_NET_RAW)?.apply()?;
+            assert!(icmp::has_cap_net_raw()?);
+            assert_eq!(unsafe { libc::setuid(0) }, -1);
+
+            icmp()?;
+
+            Ok(())
+        })?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fd_conversions() -> Result<()> {
+        #[cfg(feature = "async-tokio")]
+        use std::convert::TryFrom;
+        use std::os::unix::io::{AsFd, AsRawFd, FromRawFd, IntoRawFd, OwnedFd};
+
+        let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+        let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        recv.bind(addr)?;
+        let addr = recv.local_addr()?;
+
+        let clone = recv.try_clone()?;
+        assert_ne!(clone.as_raw_fd(), recv.as_raw_fd());
+        assert_eq!(clone.local_addr()?, addr);
+        assert_eq!(recv.as_fd().as_raw_fd(), recv.as_raw_fd());
+
+        let send = socket2::Socket::from(RawSocket::new(Domain::ipv4(), Type::dgram(), None)?);
+        let send = send.into_udp_socket();
+        send.send_to(b"std", addr)?;
+
+        let fd   = OwnedFd::from(clone);
+        let sock = RawSocket::from(fd);
+        let sock = unsafe { RawSocket::from_raw_fd(sock.into_raw_fd()) };
+
+        let mut buf = [0u8; 8];
+        let (n, from) = retry(|| sock.recv_from(&mut buf))?;
+        assert_eq!(&buf[..n], b"std");
+        assert_eq!(from.port(), send.local_addr()?.port());
+
+        #[cfg(feature = "async-tokio")]
+        block_on(async {
+            let sock  = crate::tokio::RawSocket::try_from(OwnedFd::from(sock))?;
+            let clone = sock.try_clone()?;
+
+            send.send_to(b"tokio", addr)?;
+            let (n, _) = clone.recv_from(&mut buf).await?;
+            assert_eq!(&buf[..n], b"tokio");
+
+            let sys = socket2::Socket::from(sock);
+            let sock = crate::tokio::RawSocket::try_from(sys)?;
+            assert_eq!(sock.local_addr()?, addr);
+
+            let sock = crate::RawSocket::from(sock);
+            assert_eq!(sock.local_addr()?, addr);
+
+            Ok::<_, Error>(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn socket_activation() -> Result<()> {
+        use std::env;
+        use std::os::unix::io::{AsRawFd, IntoRawFd};
+        use crate::activation::{listen_fds, LISTEN_FDS_START};
+
+        // Inherited descriptors start at fd 3, which is only safe to take
+        // over in a single-threaded forked child.
+        assert_eq!(fork(|| {
+            let udp  = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            let icmp = RawSocket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::icmpv4()));
+            let raw  = RawSocket::new(Domain::ipv4(), Type::raw(), Some(Protocol::icmpv4()));
+            let icmp = raw.or(icmp)?;
+
+            let kind = icmp.get_sockopt::<c_int>(Level::SOCKET, Name::SO_TYPE)?;
+
+            // Move the sockets clear of fds 3 and 4 before putting them there.
+            let fds = [udp.into_raw_fd(), icmp.into_raw_fd()].iter().map(|&fd| unsafe {
+                let high = libc::fcntl(fd, libc::F_DUPFD, 16);
+                libc::close(fd);
+                high
+            }).collect::<Vec<_>>();
+
+            for (n, &fd) in fds.iter().enumerate() {
+                let listen = LISTEN_FDS_START + n as c_int;
+                if fd < 0 || unsafe { libc::dup2(fd, listen) } != listen {
+                    return Err(Error::last_os_error());
+                }
+                unsafe { libc::close(fd) };
+            }
+
+            let null = std::fs::File::open("/dev/null")?.into_raw_fd();
+            let file = LISTEN_FDS_START + 2;
+            if unsafe { libc::dup2(null, file) } != file {
+                return Err(Error::last_os_error());
+            } else if null != file {
+                unsafe { libc::close(null) };
+            }
+
+            env::set_var("LISTEN_PID", "1");
+            env::set_var("LISTEN_FDS", "3");
+            assert!(listen_fds(false)?.is_empty());
+
+            env::set_var("LISTEN_PID", unsafe { libc::getpid() }.to_string());
+            env::set_var("LISTEN_FDNAMES", "udp::file");
+
+            let mut fds = listen_fds(true)?.into_iter();
+            assert!(env::var_os("LISTEN_FDS").is_none());
+            assert!(listen_fds(true)?.is_empty());
+
+            let (udp, icmp) = (fds.next().unwrap()?, fds.next().unwrap()?);
+
+            let err = fds.next().unwrap().err().expect("not a socket");
+            assert_eq!(err.raw_os_error(), Some(libc::ENOTSOCK));
+            assert!(unsafe { libc::fcntl(file, libc::F_GETFD) } >= 0);
+            assert_eq!(udp.name(), Some("udp"));
+            assert_eq!(icmp.name(), None);
+            assert_eq!(c_int::from(udp.protocol()), libc::IPPROTO_UDP);
+            assert!(icmp.is(Domain::ipv4(), kind.into(), Some(Protocol::icmpv4())));
+            assert!(!icmp.is(Domain::ipv6(), kind.into(), None));
+
+            let flags = unsafe { libc::fcntl(udp.as_raw_fd(), libc::F_GETFD) };
+            assert_eq!(flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+
+            let err = icmp.expect(Domain::ipv4(), Type::stream(), None).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+            let udp = udp.expect(Domain::ipv4(), Type::dgram(), None)?;
+            udp.bind("127.0.0.1:0")?;
+            udp.send_to(b"activated", udp.local_addr()?)?;
+
+            let mut buf = [0u8; 16];
+            let (n, _) = udp.recv_from(&mut buf)?;
+            assert_eq!(&buf[..n], b"activated");
+
+            env::set_var("LISTEN_PID", "pid");
+            env::set_var("LISTEN_FDS", "1");
+            assert_eq!(listen_fds(true).err().unwrap().kind(), ErrorKind::InvalidData);
+
+            env::set_var("LISTEN_PID", unsafe { libc::getpid() }.to_string());
+            env::set_var("LISTEN_FDS", c_int::MAX.to_string());
+            assert_eq!(listen_fds(true).err().unwrap().kind(), ErrorKind::InvalidData);
+
+            Ok(())
+        })?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "async-tokio")]
+    fn poll_try_recv_send() -> Result<()> {
+        use futures::future::poll_fn;
+        use ::tokio::io::{Interest, ReadBuf};
+
+        block_on(async {
+            let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+            let send = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            let recv = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            send.bind(addr).await?;
+            recv.bind(addr).await?;
+            let addr = recv.local_addr()?;
+
+            let mut buf = [0u8; 16];
+            let err = recv.try_recv_from(&mut buf).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::WouldBlock);
+
+            send.writable().await?;
+            assert_eq!(send.try_send_to(b"try", addr)?, 3);
+
+            recv.readable().await?;
+            let (n, from) = recv.try_recv_from(&mut buf)?;
+            assert_eq!((&buf[..n], from), (&b"try"[..], send.local_addr()?));
+
+            let n = poll_fn(|cx| send.poll_send_msg(cx, addr, &[IoSlice::new(b"poll")], None)).await?;
+            assert_eq!(n, 4);
+
+            let ready = recv.ready(Interest::READABLE | Interest::WRITABLE).await?;
+            assert!(ready.is_readable());
+
+            let mut rb = ReadBuf::new(&mut buf);
+            let from = poll_fn(|cx| recv.poll_recv_from(cx, &mut rb)).await?;
+            assert_eq!((rb.filled(), from), (&b"poll"[..], send.local_addr()?));
+
+            poll_fn(|cx| send.poll_send_to(cx, b"msg", addr)).await?;
+
+            let mut data = [0u8; 16];
+            let iovec = &[IoSliceMut::new(&mut data)];
+            let (n, _) = poll_fn(|cx| recv.poll_recv_msg(cx, iovec, None)).await?;
+            assert_eq!(n, 3);
+
+            send.try_send_msg(addr, &[IoSlice::new(b"again")], None)?;
+            recv.readable().await?;
+            let (n, _) = recv.try_recv_msg(&[IoSliceMut::new(&mut data)], None)?;
+            assert_eq!(&data[..n], b"again");
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "async-tokio"))]
+    fn framed_datagrams() -> Result<()> {
+        use bytes::Bytes;
+        use futures::{SinkExt, StreamExt};
+        use tokio_util::codec::BytesCodec;
+        use crate::control::CMsg;
+        use crate::tokio::RawFramed;
+
+        block_on(async {
+            let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+            let send = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            let recv = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            send.bind(addr).await?;
+            recv.bind(addr).await?;
+            recv.set_sockopt(Level::IPV4, Name::IPV4_PKTINFO, &1)?;
+
+            let from = send.local_addr()?;
+            let addr = recv.local_addr()?;
+
+            let mut send = RawFramed::new(send, BytesCodec::new());
+            let mut recv = RawFramed::new(recv, BytesCodec::new()).with_ctrl(128);
+
+            send.send((Bytes::from_static(b"one"), addr)).await?;
+            send.send((Bytes::from_static(b"two"), addr)).await?;
+
+            for expect in &[&b"one"[..], &b"two"[..]] {
+                let (frame, src) = recv.next().await.expect("frame")?;
+                assert_eq!((&frame[..], src), (*expect, from));
+
+                let info = recv.ctrl().find_map(|msg| match msg {
+                    CMsg::Ipv4PktInfo(info) => Some(info),
+                    _                       => None,
+                }).expect("pktinfo");
+                assert_eq!(IpAddr::from(info.addr()), addr.ip());
+            }
+
+            send.send((Bytes::from_static(b"truncated"), addr)).await?;
+
+            let mut buf = [0u8; 4];
+            let data    = &[IoSliceMut::new(&mut buf)];
+            let err     = recv.get_ref().sys().recv_datagram::<SocketAddr>(data, &mut []).expect_err("truncated");
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(feature = "async-tokio")]
+    fn split_halves() -> Result<()> {
+        block_on(async {
+            let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+            let sock = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            sock.bind(addr).await?;
+            let addr = sock.local_addr()?;
+
+            let (recv, send) = sock.split();
+            send.send_to(b"borrowed", addr).await?;
+
+            let mut buf = [0u8; 16];
+            let (n, from) = recv.recv_from(&mut buf).await?;
+            assert_eq!((&buf[..n], from), (&b"borrowed"[..], addr));
+
+            let (recv, send) = sock.into_split();
+
+            let task = ::tokio::spawn(async move {
+                let mut buf = [0u8; 16];
+                let (n, _) = recv.recv_from(&mut buf).await?;
+                assert_eq!(&buf[..n], b"owned");
+                Ok::<_, Error>(recv)
+            });
+
+            send.send_to(b"owned", addr).await?;
+            let recv = task.await.map_err(Error::other)??;
+
+            let other = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            let (other_recv, other_send) = other.into_split();
+
+            let err = recv.reunite(other_send).err().expect("mismatched halves");
+            let (recv, _) = (err.0, err.1);
+
+            let sock = send.reunite(recv).map_err(Error::other)?;
+            assert_eq!(sock.local_addr()?, addr);
+            drop(other_recv);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "async-tokio"))]
+    fn errqueue_events() -> Result<()> {
+        use futures::StreamExt;
+        use crate::control::ExtendedErr;
+        use crate::ffi::{SOF_TIMESTAMPING_OPT_ID, SOF_TIMESTAMPING_OPT_TSONLY};
+        use crate::ffi::{SOF_TIMESTAMPING_SOFTWARE, SOF_TIMESTAMPING_TX_SOFTWARE};
+
+        netns(&["link set lo up"], || block_on(async {
+            let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+            let closed = {
+                let sock = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+                sock.bind(addr)?;
+                sock.local_addr()?
+            };
+
+            let sock = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            sock.bind(addr).await?;
+            sock.set_sockopt(Level::IPV4, Name::IPV4_RECVERR, &1)?;
+            sock.send_to(b"refused", closed).await?;
+
+            let mut errors = Box::pin(sock.errqueue(64));
+
+            let msg = errors.next().await.expect("icmp error")?;
+            let err = msg.err.expect("extended error");
+            assert_eq!(err.origin(), ExtendedErr::ORIGIN_ICMP);
+            assert_eq!(err.errno(), libc::ECONNREFUSED);
+            assert_eq!((err.kind(), err.code()), (3, 3));
+            assert_eq!(err.offender().map(|a| a.ip()), Some(closed.ip()));
+            assert_eq!(msg.addr, Some(closed));
+            assert!(msg.data.ends_with(b"refused"));
+            drop(errors);
+
+            let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            recv.bind(addr)?;
+            let open = recv.local_addr()?;
+
+            let flags = SOF_TIMESTAMPING_TX_SOFTWARE | SOF_TIMESTAMPING_SOFTWARE
+                | SOF_TIMESTAMPING_OPT_ID | SOF_TIMESTAMPING_OPT_TSONLY;
+            sock.set_sockopt(Level::SOCKET, Name::SO_TIMESTAMPING, &(flags as c_int))?;
+
+            sock.send_to(b"stamp", open).await?;
+            sock.send_to(b"stamp", open).await?;
+
+            let mut stamps = Box::pin(sock.errqueue(64));
+            for key in 0..2 {
+                let msg = stamps.next().await.expect("tx timestamp")?;
+                let err = msg.err.expect("extended error");
+                assert_eq!(err.origin(), ExtendedErr::ORIGIN_TIMESTAMPING);
+                assert_eq!(err.errno(), libc::ENOMSG);
+                assert_eq!(err.data(), key);
+                assert!(msg.timestamps.and_then(|ts| ts.software()).is_some());
+            }
+
+            Ok(())
+        }))?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "async-io")]
+    fn async_io_send_recv() -> Result<()> {
+        use crate::async_io::RawSocket;
+
+        ::async_io::block_on(async {
+            let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+            let send = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            send.bind(addr).await?;
+            recv.bind(addr).await?;
+
+            recv.set_sockopt(Level::SOCKET, Name::SO_RCVBUF, &4096)?;
+            assert!(recv.get_sockopt::<c_int>(Level::SOCKET, Name::SO_RCVBUF)? >= 4096);
+
+            let addr = recv.local_addr()?;
+            let from = send.local_addr()?;
+
+            send.send_to(b"to", addr).await?;
+            send.send_msg(addr, &[IoSlice::new(b"msg")], None).await?;
+
+            let mut buf = [0u8; 16];
+            let (n, src) = recv.recv_from(&mut buf).await?;
+            assert_eq!((&buf[..n], src), (&b"to"[..], from));
+
+            let iovec = &[IoSliceMut::new(&mut buf)];
+            let (n, src) = recv.recv_msg(iovec, None).await?;
+            assert_eq!((n, src), (3, from));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "mio"))]
+    fn mio_event_loop() -> Result<()> {
+        use mio::{Events, Interest, Poll, Token};
+        use crate::Protocol;
+
+        netns(&["link set lo up"], || {
+            let mut poll   = Poll::new()?;
+            let mut events = Events::with_capacity(8);
+
+            let protocols = [253, 254];
+            let mut socks = protocols.iter().map(|proto| {
+                let sock = RawSocket::new(Domain::ipv4(), Type::raw(), Some(Protocol::from(*proto)))?;
+                sock.set_nonblocking(true)?;
+                Ok(sock)
+            }).collect::<Result<Vec<_>>>()?;
+
+            for (n, sock) in socks.iter_mut().enumerate() {
+                poll.registry().register(sock, Token(n), Interest::READABLE | Interest::WRITABLE)?;
+            }
+
+            let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+            let mut sent = [false; 2];
+            let mut recv = [None; 2];
+
+            while recv.iter().any(Option::is_none) {
+                poll.poll(&mut events, Some(Duration::from_secs(1)))?;
+                assert!(!events.is_empty(), "timed out");
+
+                for event in events.iter() {
+                    let Token(n) = event.token();
+                    let sock = &socks[n];
+
+                    if event.is_writable() && !sent[n] {
+                        sock.send_msg(addr, &[IoSlice::new(&[n as u8; 4])], &[])?;
+                        sent[n] = true;
+                    }
+
+                    while event.is_readable() {
+                        let mut buf = [0u8; 64];
+                        match sock.recv_msg(&[IoSliceMut::new(&mut buf)], &mut []) {
+                            Ok((len, from)) => recv[n] = Some((buf[len - 1], buf[9], from)),
+                            Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+            }
+
+            for (n, proto) in protocols.iter().enumerate() {
+                assert_eq!(recv[n], Some((n as u8, *proto as u8, addr)));
+            }
+
+            for sock in &mut socks {
+                poll.registry().deregister(sock)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn uring_send_recv() -> Result<()> {
+        use std::net::Ipv4Addr;
+        use crate::control::CMsg;
+        use crate::uring::UringSocket;
+
+        let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+        let send = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        send.bind(addr)?;
+        recv.bind(addr)?;
+
+        let enable: c_int = 1;
+        recv.set_sockopt(Level::IPV4, Name::IPV4_PKTINFO, &enable)?;
+
+        let addr = recv.local_addr()?;
+        let from = send.local_addr()?;
+
+        let mut send = UringSocket::new(send, 8)?;
+        let mut recv = UringSocket::new(recv, 8)?;
+
+        let pktinfo = |ctrl: &[u8]| CMsg::decode(ctrl).find_map(|msg| match msg {
+            CMsg::Ipv4PktInfo(info) => Some(info.addr()),
+            _                       => None,
+        });
+
+        send.send_msg(addr, &[IoSlice::new(b"msg")], &[])?;
+
+        let mut buf  = [0u8; 16];
+        let mut ctrl = [0u8; 64];
+        let iovec = &[IoSliceMut::new(&mut buf)];
+        let (n, src) = recv.recv_msg(iovec, &mut ctrl)?;
+        assert_eq!((&buf[..n], src), (&b"msg"[..], from));
+        assert_eq!(pktinfo(&ctrl), Some(Ipv4Addr::LOCALHOST));
+
+        send.register_buffers(vec![vec![0u8; 64]])?;
+        send.buffer_mut(0).expect("buffer")[..5].copy_from_slice(b"fixed");
+        assert_eq!(send.send_fixed(addr, 0, 5)?, 5);
+
+        let msgs = (0..12u8).map(|n| (addr, [n])).collect::<Vec<_>>();
+        assert_eq!(send.send_batch(&msgs)?, msgs.len());
+
+        let mut received = Vec::new();
+        recv.provide_buffers(4, 512)?;
+        recv.recv_multishot(64, |data, src: SocketAddr, ctrl| {
+            assert_eq!(src, from);
+            assert_eq!(pktinfo(ctrl), Some(Ipv4Addr::LOCALHOST));
+            received.push(data.to_vec());
+            received.len() <= msgs.len()
+        })?;
+
+        assert_eq!(received[0], b"fixed");
+        assert_eq!(&received[1..], msgs.iter().map(|(_, n)| n.to_vec()).collect::<Vec<_>>().as_slice());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn uring_interrupted() -> Result<()> {
+        use std::thread;
+        use crate::uring::UringSocket;
+
+        extern "C" fn handler(_: c_int) {}
+
+        let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+        let send = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        send.bind(addr)?;
+        recv.bind(addr)?;
+
+        let addr = recv.local_addr()?;
+        let mut recv = UringSocket::new(recv, 8)?;
+
+        // without SA_RESTART so the wait fails with EINTR
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(c_int) as libc::sighandler_t;
+            if libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()) != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        // the first wait also submits the receive and reports success when
+        // interrupted, so signal again while only waiting
+        let this   = unsafe { libc::pthread_self() } as usize;
+        let thread = thread::spawn(move || {
+            for _ in 0..3 {
+                thread::sleep(Duration::from_millis(50));
+                unsafe { libc::pthread_kill(this as libc::pthread_t, libc::SIGUSR1) };
+            }
+            thread::sleep(Duration::from_millis(50));
+            send.send_to(b"interrupted", addr)
+        });
+
+        let mut buf = [0u8; 16];
+        let (n, _) = recv.recv_msg(&[IoSliceMut::new(&mut buf)], &mut [])?;
+        assert_eq!(&buf[..n], b"interrupted");
+        thread.join().unwrap()?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn zerocopy_completions() -> Result<()> {
+        use crate::zerocopy::ZeroCopy;
+
+        let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+        let send = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+        send.bind(addr)?;
+        recv.bind(addr)?;
+
+        let addr = recv.local_addr()?;
+        let mut zc = ZeroCopy::new(send)?;
+
+        let a = zc.send_to(b"zero".to_vec(), addr)?;
+        let b = zc.send_to(b"copy".to_vec(), addr)?;
+        assert_eq!((a.id(), b.id()), (0, 1));
+
+        assert_eq!(zc.wait(b)?, b"copy");
+        let released = zc.complete()?;
+        assert_eq!(released, vec![(a, b"zero".to_vec())]);
+        assert_eq!(zc.pending(), 0);
+        assert!(zc.copied());
+
+        let err = zc.wait(a).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let mut buf = [0u8; 16];
+        assert_eq!(recv.recv_from(&mut buf)?.0, 4);
+        assert_eq!(&buf[..4], b"zero");
+
+        let c = zc.send_to(b"late".to_vec(), addr)?;
+        let (_, bufs) = zc.into_inner();
+        assert_eq!(bufs, vec![(c, b"late".to_vec())]);
+
+        let mut zc = ZeroCopy::new(RawSocket::new(Domain::ipv4(), Type::dgram(), None)?)?;
+        zc.send_to(b"drop".to_vec(), addr)?;
+        drop(zc);
+
+        #[cfg(feature = "async-tokio")]
+        block_on(async {
+            use std::sync::Arc;
+            use crate::tokio::zerocopy::ZeroCopy;
+
+            let send = crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            send.bind("127.0.0.1:0").await?;
+
+            let zc = Arc::new(ZeroCopy::new(send)?);
+            let mut tasks = Vec::new();
+            for n in 0..8u8 {
+                let token = zc.send_to(vec![n; 32], addr).await?;
+                let zc    = zc.clone();
+                tasks.push(::tokio::spawn(async move { zc.released(token).await }));
+            }
+
+            for (n, task) in tasks.into_iter().enumerate() {
+                assert_eq!(task.await??, vec![n as u8; 32]);
+            }
+            assert_eq!(zc.pending(), 0);
+
+            let zc    = Arc::try_unwrap(zc).ok().expect("unique");
+            let token = zc.send_to(vec![8; 32], addr).await?;
+            let (_, bufs) = zc.into_inner();
+            assert_eq!(bufs, vec![(token, vec![8; 32])]);
+
+            let zc = ZeroCopy::new(crate::tokio::RawSocket::new(Domain::ipv4(), Type::dgram(), None)?)?;
+            zc.send_to(vec![9; 32], addr).await?;
+            drop(zc);
+
+            Ok::<_, Error>(())
+        })?;
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn udp_segmentation() -> Result<()> {
+        use crate::control::CMsg;
+        use crate::udp;
+
+        let addr = SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0);
+
+        let send = RawSocket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))?;
+        let recv = RawSocket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))?;
+        send.bind(addr)?;
+        recv.bind(addr)?;
+
+        let enable: c_int = 1;
+        recv.set_sockopt(Level::UDP, Name::UDP_GRO, &enable)?;
+        assert_eq!(recv.get_sockopt::<c_int>(Level::UDP, Name::UDP_GRO)?, 1);
+
+        let addr = recv.local_addr()?;
+        let data = (0..250u8).collect::<Vec<_>>();
+
+        let mut ctrl = [0u8; 64];
+        let ctrl = CMsg::encode(&mut ctrl, &[CMsg::UdpSegment(100)])?;
+        assert_eq!(send.send_msg(addr, &[IoSlice::new(&data)], ctrl)?, data.len());
+
+        send.set_sockopt(Level::UDP, Name::UDP_SEGMENT, &(100 as c_int))?;
+        assert_eq!(send.get_sockopt::<c_int>(Level::UDP, Name::UDP_SEGMENT)?, 100);
+        send.send_to(&data, addr)?;
+
+        for _ in 0..2 {
+            let mut buf  = [0u8; 1024];
+            let mut ctrl = [0u8; 64];
+            let iovec = &[IoSliceMut::new(&mut buf)];
+            let (n, _) = recv.recv_msg(iovec, &mut ctrl)?;
+
+            let size = CMsg::decode(&ctrl).find_map(|msg| match msg {
+                CMsg::UdpGro(size) => Some(size),
+                _                  => None,
+            });
+            assert_eq!((n, size), (data.len(), Some(100)));
+
+            let segments = udp::segments(&buf[..n], &ctrl).collect::<Vec<_>>();
+            assert_eq!(segments, data.chunks(100).collect::<Vec<_>>());
+        }
+
+        assert_eq!(udp::segments(&data, &[]).count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn txtime_scheduling() -> Result<()> {
+        use crate::control::{CMsg, ExtendedErr, Raw};
+        use crate::option::TxTimeConfig;
+
+        assert_eq!(ExtendedErr::ORIGIN_TXTIME, 6);
+        assert_eq!(ExtendedErr::CODE_TXTIME_INVALID_PARAM, 1);
+        assert_eq!(ExtendedErr::CODE_TXTIME_MISSED, 2);
+
+        // decode a missed deadline entry as the etf qdisc reports it
+        let time = 0x0123_4567_89ab_cdef_u64;
+        let mut err = Vec::new();
+        err.extend_from_slice(&(libc::ECANCELED as u32).to_ne_bytes());
+        err.extend_from_slice(&[ExtendedErr::ORIGIN_TXTIME, 0, ExtendedErr::CODE_TXTIME_MISSED, 0]);
+        err.extend_from_slice(&(time as u32).to_ne_bytes());
+        err.extend_from_slice(&((time >> 32) as u32).to_ne_bytes());
+
+        let mut ctrl = [0u8; 64];
+        let raw  = Raw::from(libc::IPPROTO_IP, libc::IP_RECVERR, &err);
+        let ctrl = CMsg::encode(&mut ctrl, &[CMsg::Raw(raw)])?;
+        let err  = CMsg::decode(ctrl).find_map(|msg| match msg {
+            CMsg::ExtendedErr(err) => Some(err),
+            _                      => None,
+        }).expect("txtime error");
+        assert_eq!((err.origin(), err.code()), (ExtendedErr::ORIGIN_TXTIME, ExtendedErr::CODE_TXTIME_MISSED));
+        assert_eq!(err.errno(), libc::ECANCELED);
+        assert_eq!(err.txtime(), Some(time));
+        assert_eq!(err.zerocopy(), None);
+
+        let sock = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+
+        let config = TxTimeConfig::new(libc::CLOCK_TAI).report_errors(true);
+        sock.set_sockopt(Level::SOCKET, Name::SO_TXTIME, &config)?;
+        let config = sock.get_sockopt::<TxTimeConfig>(Level::SOCKET, Name::SO_TXTIME)?;
+        assert_eq!((config.clock(), config.is_deadline(), config.reports_errors()), (libc::CLOCK_TAI, false, true));
+
+        let mut ctrl = [0u8; 64];
+        let ctrl = CMsg::encode(&mut ctrl, &[CMsg::TxTime(u64::MAX - 1)])?;
+        assert!(matches!(CMsg::decode(ctrl).next(), Some(CMsg::TxTime(t)) if t == u64::MAX - 1));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[ignore = "needs the etf qdisc (sch_etf)"]
+    fn txtime_error_reports() -> Result<()> {
+        use crate::control::{CMsg, ExtendedErr};
+        use crate::errqueue::Message;
+        use crate::option::TxTimeConfig;
+
+        let setup = &[
+            "link add va type veth peer name vb",
+            "addr add 10.0.0.1/24 dev va",
+            "link set va up",
+            "link set vb up",
+            "neigh add 10.0.0.2 lladdr 02:00:00:00:00:02 dev va nud permanent",
+        ];
+
+        netns(setup, || {
+            let etf    = "qdisc add dev va root etf clockid CLOCK_TAI delta 500000";
+            let status = Command::new("tc").args(etf.split_whitespace()).status()?;
+            if !status.success() {
+                return Err(Error::other(format!("tc {}: {}", etf, status)));
+            }
+
+            let sock = RawSocket::new(Domain::ipv4(), Type::dgram(), None)?;
+            sock.bind("10.0.0.1:0")?;
+
+            let config = TxTimeConfig::new(libc::CLOCK_TAI).report_errors(true);
+            sock.set_sockopt(Level::SOCKET, Name::SO_TXTIME, &config)?;
+
+            let now = unsafe {
+                let mut ts: libc::timespec = std::mem::zeroed();
+                libc::clock_gettime(libc::CLOCK_TAI, &mut ts);
+                ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+            };
+
+            let send = |time: u64| -> Result<usize> {
+                let mut ctrl = [0u8; 64];
+                let ctrl = CMsg::encode(&mut ctrl, &[CMsg::TxTime(time)])?;
+                sock.send_msg("10.0.0.2:9", &[IoSlice::new(b"txtime")], ctrl)
+            };
+
+            send(now + 10_000_000)?;
+            send(now - 10_000_000)?;
+
+            let mut buf  = [0u8; 64];
+            let mut ctrl = [0u8; 128];
+            let iovec = &[IoSliceMut::new(&mut buf)];
+            let (n, addr) = retry(|| sock.recv_errqueue(iovec, &mut ctrl))?;
+
+            let msg = Message::parse(&buf[..n], &ctrl, &addr);
+            let err = msg.err.expect("txtime error");
+            assert_eq!(err.origin(), ExtendedErr::ORIGIN_TXTIME);
+            assert_eq!(err.code(), ExtendedErr::CODE_TXTIME_INVALID_PARAM);
+            assert_eq!(err.errno(), libc::ECANCELED);
+            assert_eq!(err.txtime(), Some(now - 10_000_000));
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
 }
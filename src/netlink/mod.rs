@@ -0,0 +1,306 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::convert::TryInto;
+use std::io;
+use std::iter;
+use std::str;
+use libc::c_int;
+use crate::addr::NetlinkAddr;
+use crate::packet::Error;
+use crate::{Domain, Protocol, RawSocket, Type};
+
+pub mod route;
+
+pub const NETLINK_ROUTE: c_int = 0;
+
+pub const NLMSG_NOOP:    u16 = 1;
+pub const NLMSG_ERROR:   u16 = 2;
+pub const NLMSG_DONE:    u16 = 3;
+pub const NLMSG_OVERRUN: u16 = 4;
+
+pub const NLM_F_REQUEST: u16 = 0x001;
+pub const NLM_F_MULTI:   u16 = 0x002;
+pub const NLM_F_ACK:     u16 = 0x004;
+pub const NLM_F_ECHO:    u16 = 0x008;
+pub const NLM_F_ROOT:    u16 = 0x100;
+pub const NLM_F_MATCH:   u16 = 0x200;
+pub const NLM_F_DUMP:    u16 = NLM_F_ROOT | NLM_F_MATCH;
+pub const NLM_F_REPLACE: u16 = 0x100;
+pub const NLM_F_EXCL:    u16 = 0x200;
+pub const NLM_F_CREATE:  u16 = 0x400;
+pub const NLM_F_APPEND:  u16 = 0x800;
+
+/// Receive buffer size large enough for any single netlink datagram.
+pub const BUFFER_SIZE: usize = 32 * 1024;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NlMsg<'a> {
+    pub kind:    u16,
+    pub flags:   u16,
+    pub seq:     u32,
+    pub pid:     u32,
+    pub payload: &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Nla<'a> {
+    pub kind: u16,
+    pub data: &'a [u8],
+}
+
+/// Tracks the replies to a single request, by sequence number, until the
+/// kernel signals completion with `NLMSG_DONE`, an ack, or an error.
+#[derive(Copy, Clone, Debug)]
+pub struct Transaction {
+    seq: u32,
+    ack: bool,
+}
+
+pub fn socket(protocol: c_int) -> io::Result<RawSocket> {
+    RawSocket::new(Domain::from(crate::ffi::AF_NETLINK), Type::raw(), Some(Protocol::from(protocol)))
+}
+
+/// Send `msg` to the kernel and pass each reply to `f` until the request
+/// is complete. Multicast notifications received meanwhile are dropped.
+pub fn request<F>(sock: &RawSocket, msg: &NlMsg<'_>, mut f: F) -> io::Result<()>
+where
+    F: FnMut(NlMsg<'_>) -> io::Result<()>,
+{
+    let mut buf = vec![0u8; BUFFER_SIZE.max(msg.size())];
+
+    let pkt = msg.encode(&mut buf)?;
+    sock.send_to(pkt, NetlinkAddr::kernel())?;
+
+    let tx = Transaction::new(msg);
+
+    loop {
+        let (n, from) = sock.recv_from_as::<NetlinkAddr>(&mut buf)?;
+        if from.groups() != 0 {
+            continue;
+        }
+
+        if tx.handle(&buf[..n], &mut f)? {
+            return Ok(());
+        }
+    }
+}
+
+impl<'a> NlMsg<'a> {
+    pub const HEADER_SIZE: usize = 16;
+
+    pub fn new(kind: u16, flags: u16, seq: u32, payload: &'a [u8]) -> Self {
+        let flags = flags | NLM_F_REQUEST;
+        Self { kind, flags, seq, pid: 0, payload }
+    }
+
+    pub fn size(&self) -> usize {
+        Self::HEADER_SIZE + self.payload.len()
+    }
+
+    pub fn encode<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+        let n   = self.size();
+        let pkt = buf.get_mut(..align(n)).ok_or(Error::BufferSize)?;
+
+        pkt[0..4].copy_from_slice(&(n as u32).to_ne_bytes());
+        pkt[4..6].copy_from_slice(&self.kind.to_ne_bytes());
+        pkt[6..8].copy_from_slice(&self.flags.to_ne_bytes());
+        pkt[8..12].copy_from_slice(&self.seq.to_ne_bytes());
+        pkt[12..16].copy_from_slice(&self.pid.to_ne_bytes());
+        pkt[16..n].copy_from_slice(self.payload);
+        pkt[n..].iter_mut().for_each(|b| *b = 0);
+
+        Ok(pkt)
+    }
+
+    /// Iterate over the messages in a received datagram, stopping after the
+    /// first malformed one.
+    pub fn decode(buf: &'a [u8]) -> impl Iterator<Item = Result<NlMsg<'a>, Error>> {
+        let mut rest = buf;
+
+        iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+
+            let msg = Self::read(rest);
+            rest = match msg {
+                Ok(ref msg) => rest.get(align(msg.size())..).unwrap_or(&[]),
+                Err(_)      => &[],
+            };
+
+            Some(msg)
+        })
+    }
+
+    /// Positive errno carried by an `NLMSG_ERROR` or `NLMSG_DONE` message,
+    /// or zero for an ack.
+    pub fn error(&self) -> Option<i32> {
+        match self.kind {
+            NLMSG_ERROR | NLMSG_DONE => Some(-ne_i32(self.payload).unwrap_or(0)),
+            _                        => None,
+        }
+    }
+
+    fn read(buf: &'a [u8]) -> Result<Self, Error> {
+        let len = ne_u32(buf).ok_or(Error::Truncated)? as usize;
+
+        if len < Self::HEADER_SIZE || len > buf.len() {
+            return Err(Error::Truncated);
+        }
+
+        Ok(Self {
+            kind:    u16::from_ne_bytes(buf[4..6].try_into().expect("u16")),
+            flags:   u16::from_ne_bytes(buf[6..8].try_into().expect("u16")),
+            seq:     u32::from_ne_bytes(buf[8..12].try_into().expect("u32")),
+            pid:     u32::from_ne_bytes(buf[12..16].try_into().expect("u32")),
+            payload: &buf[Self::HEADER_SIZE..len],
+        })
+    }
+}
+
+impl<'a> Nla<'a> {
+    pub const HEADER_SIZE: usize = 4;
+
+    pub const NESTED:        u16 = 1 << 15;
+    pub const NET_BYTEORDER: u16 = 1 << 14;
+    pub const TYPE_MASK:     u16 = !(Self::NESTED | Self::NET_BYTEORDER);
+
+    pub const fn new(kind: u16, data: &'a [u8]) -> Self {
+        Self { kind, data }
+    }
+
+    pub fn encode<'b>(buf: &'b mut [u8], attrs: &[Nla]) -> Result<&'b [u8], Error> {
+        let mut n = 0;
+
+        for attr in attrs {
+            let len = Self::HEADER_SIZE + attr.data.len();
+            let dst = buf.get_mut(n..n + align(len)).ok_or(Error::BufferSize)?;
+
+            dst[0..2].copy_from_slice(&(len as u16).to_ne_bytes());
+            dst[2..4].copy_from_slice(&attr.kind.to_ne_bytes());
+            dst[4..len].copy_from_slice(attr.data);
+            dst[len..].iter_mut().for_each(|b| *b = 0);
+
+            n += dst.len();
+        }
+
+        Ok(&buf[..n])
+    }
+
+    pub fn decode(buf: &'a [u8]) -> impl Iterator<Item = Nla<'a>> {
+        let mut rest = buf;
+
+        iter::from_fn(move || {
+            let len  = u16::from_ne_bytes(rest.get(0..2)?.try_into().ok()?) as usize;
+            let kind = u16::from_ne_bytes(rest.get(2..4)?.try_into().ok()?);
+
+            if len < Self::HEADER_SIZE || len > rest.len() {
+                return None;
+            }
+
+            let data = &rest[Self::HEADER_SIZE..len];
+            rest = rest.get(align(len)..).unwrap_or(&[]);
+
+            Some(Self { kind, data })
+        })
+    }
+
+    /// Attribute type without the nested and byte order flags.
+    pub fn id(&self) -> u16 {
+        self.kind & Self::TYPE_MASK
+    }
+
+    pub fn is_nested(&self) -> bool {
+        self.kind & Self::NESTED != 0
+    }
+
+    pub fn nested(&self) -> impl Iterator<Item = Nla<'a>> {
+        Self::decode(self.data)
+    }
+
+    pub fn u8(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    pub fn u16(&self) -> Option<u16> {
+        Some(u16::from_ne_bytes(self.data.get(..2)?.try_into().ok()?))
+    }
+
+    pub fn u32(&self) -> Option<u32> {
+        ne_u32(self.data)
+    }
+
+    pub fn i32(&self) -> Option<i32> {
+        ne_i32(self.data)
+    }
+
+    pub fn u64(&self) -> Option<u64> {
+        Some(u64::from_ne_bytes(self.data.get(..8)?.try_into().ok()?))
+    }
+
+    pub fn str(&self) -> Option<&'a str> {
+        let data = match self.data.iter().position(|b| *b == 0) {
+            Some(n) => &self.data[..n],
+            None    => self.data,
+        };
+        str::from_utf8(data).ok()
+    }
+}
+
+impl Transaction {
+    pub fn new(msg: &NlMsg<'_>) -> Self {
+        let seq = msg.seq;
+        let ack = msg.flags & NLM_F_ACK != 0;
+        Self { seq, ack }
+    }
+
+    /// Pass each reply in `buf` to `f`, returning true once the request
+    /// is complete. Kernel errors are returned as `io::Error`s. Replies to
+    /// earlier requests, such as those left by a cancelled query, are
+    /// skipped, while a reply to a later request is an error.
+    pub fn handle<'b, F>(&self, buf: &'b [u8], f: &mut F) -> io::Result<bool>
+    where
+        F: FnMut(NlMsg<'b>) -> io::Result<()>,
+    {
+        for msg in NlMsg::decode(buf) {
+            let msg = msg?;
+
+            match self.seq.wrapping_sub(msg.seq) as i32 {
+                0          => (),
+                n if n > 0 => continue,
+                _          => {
+                    let err = format!("netlink reply seq {} while waiting for {}", msg.seq, self.seq);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+                },
+            }
+
+            match msg.kind {
+                NLMSG_NOOP    => continue,
+                NLMSG_OVERRUN => return Err(io::Error::other("netlink overrun")),
+                NLMSG_ERROR | NLMSG_DONE => return match msg.error() {
+                    Some(0) | None => Ok(true),
+                    Some(errno)    => Err(io::Error::from_raw_os_error(errno)),
+                },
+                _ => f(msg)?,
+            }
+
+            if msg.flags & NLM_F_MULTI == 0 && !self.ack {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+fn align(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+fn ne_u32(buf: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(buf.get(..4)?.try_into().ok()?))
+}
+
+fn ne_i32(buf: &[u8]) -> Option<i32> {
+    Some(i32::from_ne_bytes(buf.get(..4)?.try_into().ok()?))
+}
@@ -0,0 +1,404 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use libc::{AF_INET, AF_INET6};
+use crate::addr::MacAddr;
+use crate::RawSocket;
+use super::{NETLINK_ROUTE, NLM_F_DUMP, NlMsg, Nla};
+
+pub const RTM_NEWLINK:  u16 = 16;
+pub const RTM_DELLINK:  u16 = 17;
+pub const RTM_GETLINK:  u16 = 18;
+pub const RTM_NEWADDR:  u16 = 20;
+pub const RTM_DELADDR:  u16 = 21;
+pub const RTM_GETADDR:  u16 = 22;
+pub const RTM_NEWROUTE: u16 = 24;
+pub const RTM_DELROUTE: u16 = 25;
+pub const RTM_GETROUTE: u16 = 26;
+pub const RTM_NEWNEIGH: u16 = 28;
+pub const RTM_DELNEIGH: u16 = 29;
+pub const RTM_GETNEIGH: u16 = 30;
+
+pub const RTNLGRP_LINK:        u32 = 1;
+pub const RTNLGRP_NEIGH:       u32 = 3;
+pub const RTNLGRP_IPV4_IFADDR: u32 = 5;
+pub const RTNLGRP_IPV4_ROUTE:  u32 = 7;
+pub const RTNLGRP_IPV6_IFADDR: u32 = 9;
+pub const RTNLGRP_IPV6_ROUTE:  u32 = 11;
+
+const IFLA_ADDRESS: u16 = 1;
+const IFLA_IFNAME:  u16 = 3;
+const IFLA_MTU:     u16 = 4;
+
+const IFA_ADDRESS:  u16 = 1;
+const IFA_LOCAL:    u16 = 2;
+const IFA_LABEL:    u16 = 3;
+
+const RTA_DST:      u16 = 1;
+const RTA_OIF:      u16 = 4;
+const RTA_GATEWAY:  u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+const RTA_PREFSRC:  u16 = 7;
+const RTA_TABLE:    u16 = 15;
+
+const NDA_DST:      u16 = 1;
+const NDA_LLADDR:   u16 = 2;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Link {
+    pub index: u32,
+    pub name:  String,
+    pub kind:  u16,
+    pub flags: u32,
+    pub mtu:   Option<u32>,
+    pub mac:   Option<MacAddr>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Address {
+    pub index:      u32,
+    pub prefix_len: u8,
+    pub scope:      u8,
+    pub addr:       IpAddr,
+    pub local:      Option<IpAddr>,
+    pub label:      Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Route {
+    pub family:   u8,
+    pub dst:      Option<IpAddr>,
+    pub dst_len:  u8,
+    pub gateway:  Option<IpAddr>,
+    pub src:      Option<IpAddr>,
+    pub oif:      Option<u32>,
+    pub table:    u32,
+    pub protocol: u8,
+    pub scope:    u8,
+    pub kind:     u8,
+    pub priority: Option<u32>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Neighbor {
+    pub index: u32,
+    pub state: u16,
+    pub flags: u8,
+    pub addr:  IpAddr,
+    pub mac:   Option<MacAddr>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    NewLink(Link),
+    DelLink(Link),
+    NewAddress(Address),
+    DelAddress(Address),
+    NewRoute(Route),
+    DelRoute(Route),
+    NewNeighbor(Neighbor),
+    DelNeighbor(Neighbor),
+}
+
+/// Blocking rtnetlink query handle. Queries take `&mut self` since the
+/// kernel serves one request per socket at a time.
+pub struct Rtnetlink {
+    sock: RawSocket,
+    seq:  u32,
+}
+
+/// A request and the parser for its replies, shared by the blocking and
+/// async `Rtnetlink` handles.
+pub(crate) struct Query<T> {
+    kind:  u16,
+    flags: u16,
+    req:   Vec<u8>,
+    parse: fn(&NlMsg<'_>) -> Option<T>,
+}
+
+impl Rtnetlink {
+    pub fn new() -> Result<Self> {
+        let sock = super::socket(NETLINK_ROUTE)?;
+        Ok(Self { sock, seq: 0 })
+    }
+
+    pub fn links(&mut self) -> Result<Vec<Link>> {
+        self.query(Query::links())
+    }
+
+    pub fn addresses(&mut self) -> Result<Vec<Address>> {
+        self.query(Query::addresses())
+    }
+
+    pub fn routes(&mut self) -> Result<Vec<Route>> {
+        self.query(Query::routes())
+    }
+
+    pub fn neighbors(&mut self) -> Result<Vec<Neighbor>> {
+        self.query(Query::neighbors())
+    }
+
+    /// Look up the route the kernel would use to reach `dst`.
+    pub fn route_to(&mut self, dst: IpAddr) -> Result<Route> {
+        let routes = self.query(Query::route_to(dst)?)?;
+        Query::first(routes)
+    }
+
+    fn query<T>(&mut self, query: Query<T>) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let msg = query.msg(&mut self.seq);
+        super::request(&self.sock, &msg, query.collect(&mut items))?;
+        Ok(items)
+    }
+}
+
+impl<T> Query<T> {
+    fn dump(kind: u16, hdr: usize, parse: fn(&NlMsg<'_>) -> Option<T>) -> Self {
+        Self { kind, flags: NLM_F_DUMP, req: vec![0u8; hdr], parse }
+    }
+
+    /// Request message using the next sequence number from `seq`.
+    pub(crate) fn msg(&self, seq: &mut u32) -> NlMsg<'_> {
+        *seq = seq.wrapping_add(1);
+        NlMsg::new(self.kind, self.flags, *seq, &self.req)
+    }
+
+    /// Reply handler for `super::request` that appends parsed replies to
+    /// `items`.
+    pub(crate) fn collect<'a>(&self, items: &'a mut Vec<T>) -> impl FnMut(NlMsg<'_>) -> Result<()> + 'a
+    where
+        T: 'a,
+    {
+        let parse = self.parse;
+        move |msg| {
+            items.extend(parse(&msg));
+            Ok(())
+        }
+    }
+
+    pub(crate) fn first(items: Vec<T>) -> Result<T> {
+        items.into_iter().next().ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+impl Query<Link> {
+    pub(crate) fn links() -> Self {
+        Self::dump(RTM_GETLINK, 16, Link::parse)
+    }
+}
+
+impl Query<Address> {
+    pub(crate) fn addresses() -> Self {
+        Self::dump(RTM_GETADDR, 8, Address::parse)
+    }
+}
+
+impl Query<Route> {
+    pub(crate) fn routes() -> Self {
+        Self::dump(RTM_GETROUTE, 12, Route::parse)
+    }
+
+    pub(crate) fn route_to(dst: IpAddr) -> Result<Self> {
+        let mut buf = [0u8; 64];
+        let req = Route::request(&mut buf, dst)?.to_vec();
+        Ok(Self { kind: RTM_GETROUTE, flags: 0, req, parse: Route::parse })
+    }
+}
+
+impl Query<Neighbor> {
+    pub(crate) fn neighbors() -> Self {
+        Self::dump(RTM_GETNEIGH, 12, Neighbor::parse)
+    }
+}
+
+impl Link {
+    pub const IFF_UP:       u32 = libc::IFF_UP as u32;
+    pub const IFF_LOOPBACK: u32 = libc::IFF_LOOPBACK as u32;
+    pub const IFF_RUNNING:  u32 = libc::IFF_RUNNING as u32;
+
+    pub fn is_up(&self) -> bool {
+        self.flags & Self::IFF_UP != 0
+    }
+
+    pub fn parse(msg: &NlMsg<'_>) -> Option<Self> {
+        if !matches!(msg.kind, RTM_NEWLINK | RTM_DELLINK) {
+            return None;
+        }
+
+        let hdr = msg.payload.get(..16)?;
+
+        let mut link = Self {
+            index: ne_u32(&hdr[4..8])?,
+            name:  String::new(),
+            kind:  u16::from_ne_bytes([hdr[2], hdr[3]]),
+            flags: ne_u32(&hdr[8..12])?,
+            mtu:   None,
+            mac:   None,
+        };
+
+        for attr in Nla::decode(&msg.payload[16..]) {
+            match attr.id() {
+                IFLA_IFNAME  => link.name = attr.str()?.to_string(),
+                IFLA_MTU     => link.mtu  = attr.u32(),
+                IFLA_ADDRESS => link.mac  = <[u8; 6]>::try_from(attr.data).ok().map(MacAddr::from),
+                _            => (),
+            }
+        }
+
+        Some(link)
+    }
+}
+
+impl Address {
+    pub fn parse(msg: &NlMsg<'_>) -> Option<Self> {
+        if !matches!(msg.kind, RTM_NEWADDR | RTM_DELADDR) {
+            return None;
+        }
+
+        let hdr = msg.payload.get(..8)?;
+
+        let (mut addr, mut local, mut label) = (None, None, None);
+
+        for attr in Nla::decode(&msg.payload[8..]) {
+            match attr.id() {
+                IFA_ADDRESS => addr  = ip(attr.data),
+                IFA_LOCAL   => local = ip(attr.data),
+                IFA_LABEL   => label = attr.str().map(String::from),
+                _           => (),
+            }
+        }
+
+        Some(Self {
+            index:      ne_u32(&hdr[4..8])?,
+            prefix_len: hdr[1],
+            scope:      hdr[3],
+            addr:       addr.or(local)?,
+            local,
+            label,
+        })
+    }
+}
+
+impl Route {
+    pub const RT_TABLE_MAIN:  u32 = 254;
+    pub const RT_TABLE_LOCAL: u32 = 255;
+
+    pub fn parse(msg: &NlMsg<'_>) -> Option<Self> {
+        if !matches!(msg.kind, RTM_NEWROUTE | RTM_DELROUTE) {
+            return None;
+        }
+
+        let hdr = msg.payload.get(..12)?;
+
+        let mut route = Self {
+            family:   hdr[0],
+            dst:      None,
+            dst_len:  hdr[1],
+            gateway:  None,
+            src:      None,
+            oif:      None,
+            table:    u32::from(hdr[4]),
+            protocol: hdr[5],
+            scope:    hdr[6],
+            kind:     hdr[7],
+            priority: None,
+        };
+
+        for attr in Nla::decode(&msg.payload[12..]) {
+            match attr.id() {
+                RTA_DST      => route.dst      = ip(attr.data),
+                RTA_GATEWAY  => route.gateway  = ip(attr.data),
+                RTA_PREFSRC  => route.src      = ip(attr.data),
+                RTA_OIF      => route.oif      = attr.u32(),
+                RTA_PRIORITY => route.priority = attr.u32(),
+                RTA_TABLE    => route.table    = attr.u32()?,
+                _            => (),
+            }
+        }
+
+        Some(route)
+    }
+
+    pub(crate) fn request(buf: &mut [u8], dst: IpAddr) -> Result<&[u8]> {
+        let (family, len, octets) = match dst {
+            IpAddr::V4(ip) => (AF_INET,  32,  ip.octets().to_vec()),
+            IpAddr::V6(ip) => (AF_INET6, 128, ip.octets().to_vec()),
+        };
+
+        let (hdr, attrs) = buf.split_at_mut(12);
+        hdr.iter_mut().for_each(|b| *b = 0);
+        hdr[0] = family as u8;
+        hdr[1] = len;
+
+        let n = Nla::encode(attrs, &[Nla::new(RTA_DST, &octets)])?.len();
+
+        Ok(&buf[..12 + n])
+    }
+}
+
+impl Neighbor {
+    pub const NUD_INCOMPLETE: u16 = 0x01;
+    pub const NUD_REACHABLE:  u16 = 0x02;
+    pub const NUD_STALE:      u16 = 0x04;
+    pub const NUD_DELAY:      u16 = 0x08;
+    pub const NUD_PROBE:      u16 = 0x10;
+    pub const NUD_FAILED:     u16 = 0x20;
+    pub const NUD_NOARP:      u16 = 0x40;
+    pub const NUD_PERMANENT:  u16 = 0x80;
+
+    pub fn parse(msg: &NlMsg<'_>) -> Option<Self> {
+        if !matches!(msg.kind, RTM_NEWNEIGH | RTM_DELNEIGH) {
+            return None;
+        }
+
+        let hdr = msg.payload.get(..12)?;
+
+        let (mut addr, mut mac) = (None, None);
+
+        for attr in Nla::decode(&msg.payload[12..]) {
+            match attr.id() {
+                NDA_DST    => addr = ip(attr.data),
+                NDA_LLADDR => mac  = <[u8; 6]>::try_from(attr.data).ok().map(MacAddr::from),
+                _          => (),
+            }
+        }
+
+        Some(Self {
+            index: ne_u32(&hdr[4..8])?,
+            state: u16::from_ne_bytes([hdr[8], hdr[9]]),
+            flags: hdr[10],
+            addr:  addr?,
+            mac,
+        })
+    }
+}
+
+impl Event {
+    pub fn parse(msg: &NlMsg<'_>) -> Option<Self> {
+        Some(match msg.kind {
+            RTM_NEWLINK  => Self::NewLink(Link::parse(msg)?),
+            RTM_DELLINK  => Self::DelLink(Link::parse(msg)?),
+            RTM_NEWADDR  => Self::NewAddress(Address::parse(msg)?),
+            RTM_DELADDR  => Self::DelAddress(Address::parse(msg)?),
+            RTM_NEWROUTE => Self::NewRoute(Route::parse(msg)?),
+            RTM_DELROUTE => Self::DelRoute(Route::parse(msg)?),
+            RTM_NEWNEIGH => Self::NewNeighbor(Neighbor::parse(msg)?),
+            RTM_DELNEIGH => Self::DelNeighbor(Neighbor::parse(msg)?),
+            _            => return None,
+        })
+    }
+}
+
+fn ip(data: &[u8]) -> Option<IpAddr> {
+    if let Ok(octets) = <[u8; 4]>::try_from(data) {
+        return Some(Ipv4Addr::from(octets).into());
+    }
+    <[u8; 16]>::try_from(data).ok().map(|octets| Ipv6Addr::from(octets).into())
+}
+
+fn ne_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(data.get(..4)?.try_into().ok()?))
+}
+
@@ -0,0 +1,74 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::fs::File;
+use std::io::{Error, Result};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Network namespace identified by its `/var/run/netns` name, a path to
+/// a namespace file, or an open namespace file descriptor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Netns {
+    Name(String),
+    Path(PathBuf),
+    Fd(RawFd),
+}
+
+impl Netns {
+    pub const RUN_DIR: &'static str = "/var/run/netns";
+
+    /// Run `f` on a new thread that has joined this namespace, leaving the
+    /// calling thread's namespace unchanged.
+    pub fn run<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce() -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let open = |path: &Path| -> Result<(Option<File>, RawFd)> {
+            let file = File::open(path)?;
+            let fd   = file.as_raw_fd();
+            Ok((Some(file), fd))
+        };
+
+        let (_file, fd) = match self {
+            Self::Name(name) => open(&Path::new(Self::RUN_DIR).join(name))?,
+            Self::Path(path) => open(path)?,
+            Self::Fd(fd)     => (None, *fd),
+        };
+
+        thread::spawn(move || {
+            if unsafe { libc::setns(fd, libc::CLONE_NEWNET) } != 0 {
+                return Err(Error::last_os_error());
+            }
+            f()
+        }).join().map_err(|_| Error::other("netns thread panicked"))?
+    }
+}
+
+impl From<&str> for Netns {
+    fn from(name: &str) -> Self {
+        match name.contains('/') {
+            true  => Self::Path(name.into()),
+            false => Self::Name(name.into()),
+        }
+    }
+}
+
+impl From<&Path> for Netns {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+impl From<PathBuf> for Netns {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<RawFd> for Netns {
+    fn from(fd: RawFd) -> Self {
+        Self::Fd(fd)
+    }
+}
@@ -1,5 +1,6 @@
 // Copyright (C) 2020 - Will Glozer. All rights reserved.
 
+use std::fmt;
 use libc::{self, c_int};
 use crate::ffi;
 
@@ -12,6 +13,13 @@ impl Level {
     pub const IPV6:   Level = Level(ffi::IPPROTO_IPV6);
     pub const SOCKET: Level = Level(ffi::SOL_SOCKET);
 
+    #[cfg(target_os = "linux")]
+    pub const NETLINK: Level = Level(ffi::SOL_NETLINK);
+    #[cfg(target_os = "linux")]
+    pub const PACKET:  Level = Level(ffi::SOL_PACKET);
+    #[cfg(target_os = "linux")]
+    pub const UDP:     Level = Level(ffi::SOL_UDP);
+
     pub const fn from(n: c_int) -> Self {
         Self(n)
     }
@@ -22,23 +30,151 @@ impl Level {
 pub struct Name(c_int);
 
 impl Name {
-    pub const IPV4_HDRINCL:      Name = Name(ffi::IP_HDRINCL);
-    pub const IPV6_CHECKSUM:     Name = Name(ffi::IPV6_CHECKSUM);
-    pub const IPV6_RECVHOPLIMIT: Name = Name(ffi::IPV6_RECVHOPLIMIT);
-    pub const IPV6_RECVPATHMTU:  Name = Name(ffi::IPV6_RECVPATHMTU);
-    pub const IPV6_RECVPKTINFO:  Name = Name(ffi::IPV6_RECVPKTINFO);
-    pub const IPV6_DONTFRAG:     Name = Name(ffi::IPV6_DONTFRAG);
-
-    pub const SO_TYPE:           Name = Name(libc::SO_TYPE);
-    pub const SO_KEEPALIVE:      Name = Name(libc::SO_KEEPALIVE);
-    pub const SO_SNDBUF:         Name = Name(libc::SO_SNDBUF);
-    pub const SO_RCVBUF:         Name = Name(libc::SO_RCVBUF);
+    pub const IPV4_HDRINCL:            Name = Name(ffi::IP_HDRINCL);
+    pub const IPV6_CHECKSUM:           Name = Name(ffi::IPV6_CHECKSUM);
+    pub const IPV6_RECVHOPLIMIT:       Name = Name(ffi::IPV6_RECVHOPLIMIT);
+    pub const IPV6_RECVPATHMTU:        Name = Name(ffi::IPV6_RECVPATHMTU);
+    pub const IPV6_RECVPKTINFO:        Name = Name(ffi::IPV6_RECVPKTINFO);
+    pub const IPV6_DONTFRAG:           Name = Name(ffi::IPV6_DONTFRAG);
+    pub const IPV6_UNICAST_HOPS:       Name = Name(libc::IPV6_UNICAST_HOPS);
+    pub const IPV6_MULTICAST_HOPS:     Name = Name(libc::IPV6_MULTICAST_HOPS);
+    pub const IPV6_MULTICAST_LOOP:     Name = Name(libc::IPV6_MULTICAST_LOOP);
+
+    pub const SO_TYPE:                 Name = Name(libc::SO_TYPE);
+    pub const SO_KEEPALIVE:            Name = Name(libc::SO_KEEPALIVE);
+    pub const SO_SNDBUF:               Name = Name(libc::SO_SNDBUF);
+    pub const SO_RCVBUF:               Name = Name(libc::SO_RCVBUF);
+
+    #[cfg(target_os = "linux")]
+    pub const IPV4_PKTINFO:            Name = Name(ffi::IP_PKTINFO);
+    #[cfg(target_os = "linux")]
+    pub const IPV4_RECVERR:            Name = Name(ffi::IP_RECVERR);
+    #[cfg(target_os = "linux")]
+    pub const IPV6_RECVERR:            Name = Name(ffi::IPV6_RECVERR);
+    #[cfg(target_os = "linux")]
+    pub const SO_BINDTODEVICE:         Name = Name(ffi::SO_BINDTODEVICE);
+    #[cfg(target_os = "linux")]
+    pub const SO_BINDTOIFINDEX:        Name = Name(ffi::SO_BINDTOIFINDEX);
+    #[cfg(target_os = "linux")]
+    pub const SO_DOMAIN:               Name = Name(ffi::SO_DOMAIN);
+    #[cfg(target_os = "linux")]
+    pub const SO_PASSCRED:             Name = Name(ffi::SO_PASSCRED);
+    #[cfg(target_os = "linux")]
+    pub const SO_PROTOCOL:             Name = Name(ffi::SO_PROTOCOL);
+    #[cfg(target_os = "linux")]
+    pub const SO_TIMESTAMPING:         Name = Name(ffi::SO_TIMESTAMPING);
+    #[cfg(target_os = "linux")]
+    pub const SO_TXTIME:               Name = Name(ffi::SO_TXTIME);
+    #[cfg(target_os = "linux")]
+    pub const SO_ZEROCOPY:             Name = Name(ffi::SO_ZEROCOPY);
+
+    #[cfg(target_os = "linux")]
+    pub const PACKET_AUXDATA:          Name = Name(ffi::PACKET_AUXDATA);
+
+    #[cfg(target_os = "linux")]
+    pub const UDP_GRO:                 Name = Name(ffi::UDP_GRO);
+    #[cfg(target_os = "linux")]
+    pub const UDP_SEGMENT:             Name = Name(ffi::UDP_SEGMENT);
+
+    #[cfg(target_os = "linux")]
+    pub const NETLINK_ADD_MEMBERSHIP:  Name = Name(ffi::NETLINK_ADD_MEMBERSHIP);
+    #[cfg(target_os = "linux")]
+    pub const NETLINK_DROP_MEMBERSHIP: Name = Name(ffi::NETLINK_DROP_MEMBERSHIP);
+    #[cfg(target_os = "linux")]
+    pub const NETLINK_EXT_ACK:         Name = Name(ffi::NETLINK_EXT_ACK);
+    #[cfg(target_os = "linux")]
+    pub const NETLINK_GET_STRICT_CHK:  Name = Name(ffi::NETLINK_GET_STRICT_CHK);
 
     pub const fn from(n: c_int) -> Self {
         Self(n)
     }
 }
 
+impl From<Level> for c_int {
+    fn from(level: Level) -> Self {
+        level.0
+    }
+}
+
+impl From<Name> for c_int {
+    fn from(name: Name) -> Self {
+        name.0
+    }
+}
+
+/// Socket option value.
+///
+/// # Safety
+///
+/// Implementors are passed to `getsockopt` and `setsockopt` by pointer and
+/// size, so they must be plain data with the layout the kernel expects.
 pub unsafe trait Opt: Copy + Default {}
 
 unsafe impl Opt for c_int {}
+unsafe impl Opt for [u8; libc::IFNAMSIZ] {}
+#[cfg(target_os = "linux")]
+unsafe impl Opt for TxTimeConfig {}
+
+/// `SO_TXTIME` value, scheduling packets sent with a `CMsg::TxTime` on
+/// a clock such as `CLOCK_TAI`.
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct TxTimeConfig(ffi::sock_txtime);
+
+#[cfg(target_os = "linux")]
+impl TxTimeConfig {
+    pub fn new(clock: libc::clockid_t) -> Self {
+        Self(ffi::sock_txtime { clockid: clock, flags: 0 })
+    }
+
+    /// Treat each transmit time as a deadline to send by rather than the
+    /// time to send at.
+    pub fn deadline(self, enable: bool) -> Self {
+        self.flag(ffi::SOF_TXTIME_DEADLINE_MODE, enable)
+    }
+
+    /// Report dropped packets through the error queue, with origin
+    /// `ExtendedErr::ORIGIN_TXTIME`.
+    pub fn report_errors(self, enable: bool) -> Self {
+        self.flag(ffi::SOF_TXTIME_REPORT_ERRORS, enable)
+    }
+
+    pub fn clock(&self) -> libc::clockid_t {
+        self.0.clockid
+    }
+
+    pub fn is_deadline(&self) -> bool {
+        self.0.flags & ffi::SOF_TXTIME_DEADLINE_MODE != 0
+    }
+
+    pub fn reports_errors(&self) -> bool {
+        self.0.flags & ffi::SOF_TXTIME_REPORT_ERRORS != 0
+    }
+
+    fn flag(mut self, flag: u32, enable: bool) -> Self {
+        match enable {
+            true  => self.0.flags |= flag,
+            false => self.0.flags &= !flag,
+        }
+        self
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Default for TxTimeConfig {
+    fn default() -> Self {
+        Self::new(libc::CLOCK_MONOTONIC)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for TxTimeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxTimeConfig")
+            .field("clock", &self.clock())
+            .field("deadline", &self.is_deadline())
+            .field("report_errors", &self.reports_errors())
+            .finish()
+    }
+}
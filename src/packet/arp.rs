@@ -0,0 +1,97 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::net::Ipv4Addr;
+use crate::addr::MacAddr;
+use super::{Ethernet, Error, buffer, get, get_u16};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Arp {
+    pub op:  u16,
+    pub sha: MacAddr,
+    pub spa: Ipv4Addr,
+    pub tha: MacAddr,
+    pub tpa: Ipv4Addr,
+}
+
+impl Arp {
+    pub const SIZE: usize = 28;
+
+    pub const REQUEST: u16 = 1;
+    pub const REPLY:   u16 = 2;
+
+    const HTYPE_ETHER: u16 = 1;
+
+    pub fn request(sha: MacAddr, spa: Ipv4Addr, tpa: Ipv4Addr) -> Self {
+        let tha = MacAddr::ZERO;
+        Self { op: Self::REQUEST, sha, spa, tha, tpa }
+    }
+
+    pub fn reply(sha: MacAddr, spa: Ipv4Addr, tha: MacAddr, tpa: Ipv4Addr) -> Self {
+        Self { op: Self::REPLY, sha, spa, tha, tpa }
+    }
+
+    pub fn gratuitous(sha: MacAddr, ip: Ipv4Addr) -> Self {
+        Self::request(sha, ip, ip)
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.op == Self::REQUEST
+    }
+
+    pub fn is_reply(&self) -> bool {
+        self.op == Self::REPLY
+    }
+
+    pub fn is_gratuitous(&self) -> bool {
+        self.spa == self.tpa
+    }
+
+    /// Destination MAC address for the Ethernet frame carrying this message.
+    pub fn dst(&self) -> MacAddr {
+        match self.op {
+            Self::REPLY if !self.is_gratuitous() => self.tha,
+            _                                    => MacAddr::BROADCAST,
+        }
+    }
+
+    pub fn encode<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+        let pkt = buffer(buf, Self::SIZE)?;
+
+        pkt[0..2].copy_from_slice(&Self::HTYPE_ETHER.to_be_bytes());
+        pkt[2..4].copy_from_slice(&Ethernet::IPV4.to_be_bytes());
+        pkt[4..6].copy_from_slice(&[6, 4]);
+        pkt[6..8].copy_from_slice(&self.op.to_be_bytes());
+        pkt[8..14].copy_from_slice(&self.sha.octets());
+        pkt[14..18].copy_from_slice(&self.spa.octets());
+        pkt[18..24].copy_from_slice(&self.tha.octets());
+        pkt[24..28].copy_from_slice(&self.tpa.octets());
+
+        Ok(pkt)
+    }
+
+    pub fn decode(pkt: &[u8]) -> Result<Self, Error> {
+        let htype = get_u16(pkt, 0)?;
+        let ptype = get_u16(pkt, 2)?;
+        let [hlen, plen] = get(pkt, 4)?;
+
+        if (htype, ptype, hlen, plen) != (Self::HTYPE_ETHER, Ethernet::IPV4, 6, 4) {
+            return Err(Error::Unsupported);
+        }
+
+        Ok(Self {
+            op:  get_u16(pkt, 6)?,
+            sha: MacAddr::from(get(pkt, 8)?),
+            spa: Ipv4Addr::from(get::<4>(pkt, 14)?),
+            tha: MacAddr::from(get(pkt, 18)?),
+            tpa: Ipv4Addr::from(get::<4>(pkt, 24)?),
+        })
+    }
+
+    /// Encode this message into a complete Ethernet frame for `SOCK_RAW`
+    /// packet sockets.
+    pub fn encode_frame<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+        let mut arp = [0u8; Self::SIZE];
+        let arp = self.encode(&mut arp)?;
+        Ethernet::new(self.dst(), self.sha, Ethernet::ARP, arp).encode(buf)
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use crate::addr::MacAddr;
+use super::{Error, buffer, get, get_u16};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Ethernet<'a> {
+    pub dst:       MacAddr,
+    pub src:       MacAddr,
+    pub vlan:      Vlan,
+    pub ethertype: u16,
+    pub payload:   &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Vlan {
+    Untagged,
+    Tagged(VlanTag),
+    QinQ(VlanTag, VlanTag),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct VlanTag {
+    pub tpid: u16,
+    pub tci:  u16,
+}
+
+impl<'a> Ethernet<'a> {
+    pub const HEADER_SIZE: usize = 14;
+
+    pub const IPV4:     u16 = 0x0800;
+    pub const ARP:      u16 = 0x0806;
+    pub const VLAN:     u16 = 0x8100;
+    pub const IPV6:     u16 = 0x86dd;
+    pub const QINQ:     u16 = 0x88a8;
+    pub const QINQ_OLD: u16 = 0x9100;
+
+    pub fn new(dst: MacAddr, src: MacAddr, ethertype: u16, payload: &'a [u8]) -> Self {
+        let vlan = Vlan::Untagged;
+        Self { dst, src, vlan, ethertype, payload }
+    }
+
+    pub fn with_vlan(mut self, vlan: Vlan) -> Self {
+        self.vlan = vlan;
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        Self::HEADER_SIZE + self.vlan.size() + self.payload.len()
+    }
+
+    pub fn encode<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+        let pkt = buffer(buf, self.size())?;
+
+        pkt[0..6].copy_from_slice(&self.dst.octets());
+        pkt[6..12].copy_from_slice(&self.src.octets());
+
+        let mut n = 12;
+        for tag in self.vlan.tags() {
+            pkt[n..n + 2].copy_from_slice(&tag.tpid.to_be_bytes());
+            pkt[n + 2..n + 4].copy_from_slice(&tag.tci.to_be_bytes());
+            n += VlanTag::SIZE;
+        }
+
+        pkt[n..n + 2].copy_from_slice(&self.ethertype.to_be_bytes());
+        pkt[n + 2..].copy_from_slice(self.payload);
+
+        Ok(pkt)
+    }
+
+    pub fn decode(pkt: &'a [u8]) -> Result<Self, Error> {
+        let dst = MacAddr::from(get(pkt, 0)?);
+        let src = MacAddr::from(get(pkt, 6)?);
+
+        let mut vlan = Vlan::Untagged;
+        let mut n    = 12;
+
+        let ethertype = loop {
+            let kind = get_u16(pkt, n)?;
+
+            if !VlanTag::is_tpid(kind) {
+                break kind;
+            }
+
+            let tag = VlanTag::new(kind, get_u16(pkt, n + 2)?);
+
+            vlan = match vlan {
+                Vlan::Untagged       => Vlan::Tagged(tag),
+                Vlan::Tagged(outer)  => Vlan::QinQ(outer, tag),
+                Vlan::QinQ(..)       => return Err(Error::Unsupported),
+            };
+
+            n += VlanTag::SIZE;
+        };
+
+        let payload = &pkt[n + 2..];
+
+        Ok(Self { dst, src, vlan, ethertype, payload })
+    }
+}
+
+impl Vlan {
+    pub fn tags(&self) -> impl Iterator<Item = VlanTag> {
+        let (outer, inner) = match *self {
+            Vlan::Untagged            => (None, None),
+            Vlan::Tagged(tag)         => (Some(tag), None),
+            Vlan::QinQ(outer, inner)  => (Some(outer), Some(inner)),
+        };
+        outer.into_iter().chain(inner)
+    }
+
+    pub fn size(&self) -> usize {
+        self.tags().count() * VlanTag::SIZE
+    }
+}
+
+impl VlanTag {
+    pub const SIZE: usize = 4;
+
+    pub const fn new(tpid: u16, tci: u16) -> Self {
+        Self { tpid, tci }
+    }
+
+    pub const fn dot1q(vid: u16) -> Self {
+        Self::new(Ethernet::VLAN, vid & 0x0fff)
+    }
+
+    pub const fn dot1ad(vid: u16) -> Self {
+        Self::new(Ethernet::QINQ, vid & 0x0fff)
+    }
+
+    pub const fn with_pcp(mut self, pcp: u8) -> Self {
+        self.tci = (self.tci & 0x1fff) | ((pcp as u16 & 0x7) << 13);
+        self
+    }
+
+    pub const fn with_dei(mut self, dei: bool) -> Self {
+        self.tci = (self.tci & !0x1000) | ((dei as u16) << 12);
+        self
+    }
+
+    pub const fn vid(&self) -> u16 {
+        self.tci & 0x0fff
+    }
+
+    pub const fn pcp(&self) -> u8 {
+        (self.tci >> 13) as u8
+    }
+
+    pub const fn dei(&self) -> bool {
+        self.tci & 0x1000 != 0
+    }
+
+    fn is_tpid(kind: u16) -> bool {
+        matches!(kind, Ethernet::VLAN | Ethernet::QINQ | Ethernet::QINQ_OLD)
+    }
+}
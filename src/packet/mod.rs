@@ -0,0 +1,53 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::io;
+
+pub use self::arp::Arp;
+pub use self::ether::{Ethernet, Vlan, VlanTag};
+pub use self::ndp::{Ndp, NdpOption};
+
+pub mod arp;
+pub mod ether;
+pub mod ndp;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    BufferSize,
+    Truncated,
+    Unsupported,
+}
+
+fn get<const N: usize>(pkt: &[u8], at: usize) -> Result<[u8; N], Error> {
+    match pkt.get(at..at + N) {
+        Some(bytes) => Ok(bytes.try_into().expect("slice size")),
+        None        => Err(Error::Truncated),
+    }
+}
+
+fn get_u16(pkt: &[u8], at: usize) -> Result<u16, Error> {
+    get(pkt, at).map(u16::from_be_bytes)
+}
+
+fn buffer(buf: &mut [u8], n: usize) -> Result<&mut [u8], Error> {
+    buf.get_mut(..n).ok_or(Error::BufferSize)
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match err {
+            Error::BufferSize => io::ErrorKind::InvalidInput,
+            _                 => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -0,0 +1,403 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::iter;
+use std::net::Ipv6Addr;
+use std::slice;
+use std::time::Duration;
+use crate::addr::MacAddr;
+use super::{Error, buffer, get, get_u16};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Ndp<'a> {
+    RouterSolicit(RouterSolicit<'a>),
+    RouterAdvert(RouterAdvert<'a>),
+    NeighborSolicit(NeighborSolicit<'a>),
+    NeighborAdvert(NeighborAdvert<'a>),
+    Redirect(Redirect<'a>),
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RouterSolicit<'a> {
+    pub options: &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RouterAdvert<'a> {
+    pub hop_limit:       u8,
+    pub managed:         bool,
+    pub other:           bool,
+    pub router_lifetime: u16,
+    pub reachable_time:  u32,
+    pub retrans_timer:   u32,
+    pub options:         &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NeighborSolicit<'a> {
+    pub target:  Ipv6Addr,
+    pub options: &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NeighborAdvert<'a> {
+    pub router:    bool,
+    pub solicited: bool,
+    pub overrides: bool,
+    pub target:    Ipv6Addr,
+    pub options:   &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Redirect<'a> {
+    pub target:  Ipv6Addr,
+    pub dest:    Ipv6Addr,
+    pub options: &'a [u8],
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NdpOption<'a> {
+    SourceLinkAddr(MacAddr),
+    TargetLinkAddr(MacAddr),
+    PrefixInfo(PrefixInfo),
+    RedirectedHeader(&'a [u8]),
+    Mtu(u32),
+    Rdnss(u32, &'a [[u8; 16]]),
+    Raw(u8, &'a [u8]),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PrefixInfo {
+    pub prefix:             Ipv6Addr,
+    pub prefix_len:         u8,
+    pub on_link:            bool,
+    pub autonomous:         bool,
+    pub valid_lifetime:     u32,
+    pub preferred_lifetime: u32,
+}
+
+/// Hop limit required on every NDP message sent and received.
+pub const HOP_LIMIT: u8 = 255;
+
+/// Upper bound on the random delay before answering a router solicitation.
+pub const MAX_RA_DELAY_TIME: Duration = Duration::from_millis(500);
+
+/// Minimum spacing between router advertisements sent to all nodes.
+pub const MIN_DELAY_BETWEEN_RAS: Duration = Duration::from_secs(3);
+
+pub const ALL_NODES:   Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+pub const ALL_ROUTERS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+
+pub fn solicited_node(addr: &Ipv6Addr) -> Ipv6Addr {
+    let [.., a, b, c] = addr.octets();
+    let mut octets = [0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xff, 0, 0, 0];
+    octets[13..].copy_from_slice(&[a, b, c]);
+    Ipv6Addr::from(octets)
+}
+
+impl<'a> Ndp<'a> {
+    pub const ROUTER_SOLICIT:   u8 = 133;
+    pub const ROUTER_ADVERT:    u8 = 134;
+    pub const NEIGHBOR_SOLICIT: u8 = 135;
+    pub const NEIGHBOR_ADVERT:  u8 = 136;
+    pub const REDIRECT:         u8 = 137;
+
+    fn kind(&self) -> u8 {
+        match self {
+            Self::RouterSolicit(..)   => Self::ROUTER_SOLICIT,
+            Self::RouterAdvert(..)    => Self::ROUTER_ADVERT,
+            Self::NeighborSolicit(..) => Self::NEIGHBOR_SOLICIT,
+            Self::NeighborAdvert(..)  => Self::NEIGHBOR_ADVERT,
+            Self::Redirect(..)        => Self::REDIRECT,
+        }
+    }
+
+    fn header_size(&self) -> usize {
+        match self {
+            Self::RouterSolicit(..)   => 8,
+            Self::RouterAdvert(..)    => 16,
+            Self::NeighborSolicit(..) => 24,
+            Self::NeighborAdvert(..)  => 24,
+            Self::Redirect(..)        => 40,
+        }
+    }
+
+    pub fn options(&self) -> &'a [u8] {
+        match self {
+            Self::RouterSolicit(rs)   => rs.options,
+            Self::RouterAdvert(ra)    => ra.options,
+            Self::NeighborSolicit(ns) => ns.options,
+            Self::NeighborAdvert(na)  => na.options,
+            Self::Redirect(r)         => r.options,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.header_size() + self.options().len()
+    }
+
+    /// Encode this message with a zero checksum, which the kernel fills in
+    /// for raw ICMPv6 sockets.
+    pub fn encode<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+        let n   = self.header_size();
+        let pkt = buffer(buf, self.size())?;
+
+        pkt[..n].iter_mut().for_each(|b| *b = 0);
+        pkt[0] = self.kind();
+
+        match self {
+            Self::RouterSolicit(..) => (),
+            Self::RouterAdvert(ra)  => {
+                pkt[4] = ra.hop_limit;
+                pkt[5] = flag(ra.managed, 0x80) | flag(ra.other, 0x40);
+                pkt[6..8].copy_from_slice(&ra.router_lifetime.to_be_bytes());
+                pkt[8..12].copy_from_slice(&ra.reachable_time.to_be_bytes());
+                pkt[12..16].copy_from_slice(&ra.retrans_timer.to_be_bytes());
+            }
+            Self::NeighborSolicit(ns) => {
+                pkt[8..24].copy_from_slice(&ns.target.octets());
+            }
+            Self::NeighborAdvert(na) => {
+                pkt[4] = flag(na.router, 0x80) | flag(na.solicited, 0x40) | flag(na.overrides, 0x20);
+                pkt[8..24].copy_from_slice(&na.target.octets());
+            }
+            Self::Redirect(r) => {
+                pkt[8..24].copy_from_slice(&r.target.octets());
+                pkt[24..40].copy_from_slice(&r.dest.octets());
+            }
+        }
+
+        pkt[n..].copy_from_slice(self.options());
+
+        Ok(pkt)
+    }
+
+    pub fn decode(pkt: &'a [u8]) -> Result<Self, Error> {
+        let [kind, code] = get(pkt, 0)?;
+
+        if code != 0 {
+            return Err(Error::Unsupported);
+        }
+
+        let addr = |at| get::<16>(pkt, at).map(Ipv6Addr::from);
+        let opts = |at| pkt.get(at..).ok_or(Error::Truncated);
+
+        Ok(match kind {
+            Self::ROUTER_SOLICIT => {
+                get::<4>(pkt, 4)?;
+                Self::RouterSolicit(RouterSolicit {
+                    options: opts(8)?,
+                })
+            }
+            Self::ROUTER_ADVERT => {
+                let [hop_limit, flags] = get(pkt, 4)?;
+                Self::RouterAdvert(RouterAdvert {
+                    hop_limit,
+                    managed:         flags & 0x80 != 0,
+                    other:           flags & 0x40 != 0,
+                    router_lifetime: get_u16(pkt, 6)?,
+                    reachable_time:  u32::from_be_bytes(get(pkt, 8)?),
+                    retrans_timer:   u32::from_be_bytes(get(pkt, 12)?),
+                    options:         opts(16)?,
+                })
+            }
+            Self::NEIGHBOR_SOLICIT => {
+                Self::NeighborSolicit(NeighborSolicit {
+                    target:  addr(8)?,
+                    options: opts(24)?,
+                })
+            }
+            Self::NEIGHBOR_ADVERT => {
+                let [flags] = get(pkt, 4)?;
+                Self::NeighborAdvert(NeighborAdvert {
+                    router:    flags & 0x80 != 0,
+                    solicited: flags & 0x40 != 0,
+                    overrides: flags & 0x20 != 0,
+                    target:    addr(8)?,
+                    options:   opts(24)?,
+                })
+            }
+            Self::REDIRECT => {
+                Self::Redirect(Redirect {
+                    target:  addr(8)?,
+                    dest:    addr(24)?,
+                    options: opts(40)?,
+                })
+            }
+            _ => return Err(Error::Unsupported),
+        })
+    }
+}
+
+impl<'a> RouterSolicit<'a> {
+    pub fn new(options: &'a [u8]) -> Self {
+        Self { options }
+    }
+}
+
+impl<'a> RouterAdvert<'a> {
+    pub fn new(router_lifetime: u16, options: &'a [u8]) -> Self {
+        Self { hop_limit: 64, router_lifetime, options, ..Self::default() }
+    }
+}
+
+impl<'a> NeighborSolicit<'a> {
+    pub fn new(target: Ipv6Addr, options: &'a [u8]) -> Self {
+        Self { target, options }
+    }
+}
+
+impl<'a> NeighborAdvert<'a> {
+    pub fn new(target: Ipv6Addr, options: &'a [u8]) -> Self {
+        let (router, solicited, overrides) = (false, false, false);
+        Self { router, solicited, overrides, target, options }
+    }
+}
+
+impl<'a> Redirect<'a> {
+    pub fn new(target: Ipv6Addr, dest: Ipv6Addr, options: &'a [u8]) -> Self {
+        Self { target, dest, options }
+    }
+}
+
+impl<'a> NdpOption<'a> {
+    pub const SOURCE_LINK_ADDR:  u8 = 1;
+    pub const TARGET_LINK_ADDR:  u8 = 2;
+    pub const PREFIX_INFO:       u8 = 3;
+    pub const REDIRECTED_HEADER: u8 = 4;
+    pub const MTU:               u8 = 5;
+    pub const RDNSS:             u8 = 25;
+
+    pub fn encode<'b>(buf: &'b mut [u8], opts: &[NdpOption]) -> Result<&'b [u8], Error> {
+        let mut n = 0;
+
+        for opt in opts {
+            let len = opt.size();
+            let dst = buf.get_mut(n..n + len).ok_or(Error::BufferSize)?;
+            dst.iter_mut().for_each(|b| *b = 0);
+            dst[0] = opt.kind();
+            dst[1] = (len / 8) as u8;
+            opt.write(&mut dst[2..]);
+            n += len;
+        }
+
+        Ok(&buf[..n])
+    }
+
+    pub fn decode(buf: &'a [u8]) -> impl Iterator<Item = NdpOption<'a>> {
+        let mut rest = buf;
+
+        iter::from_fn(move || {
+            let [kind, len] = get(rest, 0).ok()?;
+            let len = usize::from(len) * 8;
+
+            if len == 0 || len > rest.len() {
+                return None;
+            }
+
+            let (opt, tail) = rest.split_at(len);
+            rest = tail;
+
+            Some(Self::read(kind, &opt[2..]))
+        })
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Self::SourceLinkAddr(..)   => Self::SOURCE_LINK_ADDR,
+            Self::TargetLinkAddr(..)   => Self::TARGET_LINK_ADDR,
+            Self::PrefixInfo(..)       => Self::PREFIX_INFO,
+            Self::RedirectedHeader(..) => Self::REDIRECTED_HEADER,
+            Self::Mtu(..)              => Self::MTU,
+            Self::Rdnss(..)            => Self::RDNSS,
+            Self::Raw(kind, ..)        => *kind,
+        }
+    }
+
+    fn size(&self) -> usize {
+        let len = match self {
+            Self::SourceLinkAddr(..)     => 8,
+            Self::TargetLinkAddr(..)     => 8,
+            Self::PrefixInfo(..)         => 32,
+            Self::RedirectedHeader(data) => 8 + data.len(),
+            Self::Mtu(..)                => 8,
+            Self::Rdnss(_, addrs)        => 8 + addrs.len() * 16,
+            Self::Raw(_, data)           => 2 + data.len(),
+        };
+        (len + 7) & !7
+    }
+
+    fn write(&self, dst: &mut [u8]) {
+        match self {
+            Self::SourceLinkAddr(mac)    => dst[..6].copy_from_slice(&mac.octets()),
+            Self::TargetLinkAddr(mac)    => dst[..6].copy_from_slice(&mac.octets()),
+            Self::PrefixInfo(info)       => info.write(dst),
+            Self::RedirectedHeader(data) => dst[6..6 + data.len()].copy_from_slice(data),
+            Self::Mtu(mtu)               => dst[2..6].copy_from_slice(&mtu.to_be_bytes()),
+            Self::Rdnss(lifetime, addrs) => {
+                dst[2..6].copy_from_slice(&lifetime.to_be_bytes());
+                for (dst, addr) in dst[6..].chunks_exact_mut(16).zip(addrs.iter()) {
+                    dst.copy_from_slice(addr);
+                }
+            }
+            Self::Raw(_, data)           => dst[..data.len()].copy_from_slice(data),
+        }
+    }
+
+    fn read(kind: u8, data: &'a [u8]) -> Self {
+        let mac = || get(data, 0).map(MacAddr::from);
+        let opt = match kind {
+            Self::SOURCE_LINK_ADDR  => mac().map(Self::SourceLinkAddr),
+            Self::TARGET_LINK_ADDR  => mac().map(Self::TargetLinkAddr),
+            Self::PREFIX_INFO       => PrefixInfo::read(data).map(Self::PrefixInfo),
+            Self::REDIRECTED_HEADER => data.get(6..).map(Self::RedirectedHeader).ok_or(Error::Truncated),
+            Self::MTU               => get(data, 2).map(|mtu| Self::Mtu(u32::from_be_bytes(mtu))),
+            Self::RDNSS             => get(data, 2).map(|lifetime| {
+                let addrs = &data[6..];
+                let addrs = unsafe {
+                    let ptr = addrs.as_ptr() as *const [u8; 16];
+                    slice::from_raw_parts(ptr, addrs.len() / 16)
+                };
+                Self::Rdnss(u32::from_be_bytes(lifetime), addrs)
+            }),
+            _                       => Err(Error::Unsupported),
+        };
+        opt.unwrap_or(Self::Raw(kind, data))
+    }
+}
+
+impl PrefixInfo {
+    pub fn new(prefix: Ipv6Addr, prefix_len: u8) -> Self {
+        Self {
+            prefix,
+            prefix_len,
+            on_link:            true,
+            autonomous:         true,
+            valid_lifetime:     u32::MAX,
+            preferred_lifetime: u32::MAX,
+        }
+    }
+
+    fn write(&self, dst: &mut [u8]) {
+        dst[0] = self.prefix_len;
+        dst[1] = flag(self.on_link, 0x80) | flag(self.autonomous, 0x40);
+        dst[2..6].copy_from_slice(&self.valid_lifetime.to_be_bytes());
+        dst[6..10].copy_from_slice(&self.preferred_lifetime.to_be_bytes());
+        dst[14..30].copy_from_slice(&self.prefix.octets());
+    }
+
+    fn read(data: &[u8]) -> Result<Self, Error> {
+        let [prefix_len, flags] = get(data, 0)?;
+        Ok(Self {
+            prefix:             Ipv6Addr::from(get::<16>(data, 14)?),
+            prefix_len,
+            on_link:            flags & 0x80 != 0,
+            autonomous:         flags & 0x40 != 0,
+            valid_lifetime:     u32::from_be_bytes(get(data, 2)?),
+            preferred_lifetime: u32::from_be_bytes(get(data, 6)?),
+        })
+    }
+}
+
+fn flag(set: bool, bit: u8) -> u8 {
+    if set { bit } else { 0 }
+}
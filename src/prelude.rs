@@ -6,7 +6,13 @@ pub use crate::Domain;
 pub use crate::Type;
 pub use crate::Protocol;
 
+pub use crate::addr::{FromSockAddr, MacAddr, ToSockAddr};
+#[cfg(target_os = "linux")]
+pub use crate::addr::{LinkAddr, NetlinkAddr};
+
 pub use crate::control::CMsg;
 
+pub use crate::iface::Interface;
+
 pub use crate::option::Name;
 pub use crate::option::Level;
@@ -0,0 +1,148 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::io::{Error, ErrorKind, Result};
+use std::ptr;
+use libc::{c_int, c_ulong, gid_t, uid_t};
+
+pub const CAP_NET_BIND_SERVICE: u32 = 10;
+pub const CAP_NET_ADMIN:        u32 = 12;
+pub const CAP_NET_RAW:          u32 = 13;
+
+/// Identity and capabilities to switch to once sockets are set up.
+///
+/// Capabilities are per-thread, so drop privileges before starting any
+/// other threads, such as those of a multi-threaded tokio runtime.
+#[derive(Clone, Debug)]
+pub struct Privileges {
+    uid:  uid_t,
+    gid:  gid_t,
+    keep: u64,
+}
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid:     c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct CapData {
+    effective:   u32,
+    permitted:   u32,
+    inheritable: u32,
+}
+
+const CAP_VERSION_3: u32 = 0x2008_0522;
+const CAP_SETUID:    u32 = 7;
+const CAP_LAST:      u32 = 63;
+
+impl Privileges {
+    pub fn new(uid: uid_t, gid: gid_t) -> Self {
+        Self { uid, gid, keep: 0 }
+    }
+
+    /// Retain `cap` in the permitted and effective sets. Capabilities
+    /// above `CAP_LAST` are rejected with `InvalidInput`.
+    pub fn keep(mut self, cap: u32) -> Result<Self> {
+        if cap > CAP_LAST {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("invalid capability {}", cap)));
+        }
+        self.keep |= 1 << cap;
+        Ok(self)
+    }
+
+    /// Switch to the configured uid and gid, clear supplementary groups,
+    /// and drop every capability not kept, then verify the result.
+    pub fn apply(&self) -> Result<()> {
+        unsafe {
+            for cap in (0..=CAP_LAST).filter(|cap| self.keep & (1 << cap) == 0) {
+                if libc::prctl(libc::PR_CAPBSET_DROP, cap as c_ulong) != 0 {
+                    match Error::last_os_error() {
+                        e if e.raw_os_error() == Some(libc::EINVAL) => break,
+                        e                                          => return Err(e),
+                    }
+                }
+            }
+
+            check(libc::prctl(libc::PR_SET_KEEPCAPS, (self.keep != 0) as c_ulong))?;
+            check(libc::setgroups(0, ptr::null()))?;
+            check(libc::setresgid(self.gid, self.gid, self.gid))?;
+            check(libc::setresuid(self.uid, self.uid, self.uid))?;
+
+            let mut data = [CapData::default(); 2];
+            for (n, data) in data.iter_mut().enumerate() {
+                data.permitted = (self.keep >> (32 * n)) as u32;
+                data.effective = data.permitted;
+            }
+            capset(&data)?;
+
+            check(libc::prctl(libc::PR_SET_KEEPCAPS, 0 as c_ulong))?;
+        }
+
+        self.verify()
+    }
+
+    fn verify(&self) -> Result<()> {
+        let (mut ruid, mut euid, mut suid) = (0, 0, 0);
+        let (mut rgid, mut egid, mut sgid) = (0, 0, 0);
+
+        unsafe {
+            check(libc::getresuid(&mut ruid, &mut euid, &mut suid))?;
+            check(libc::getresgid(&mut rgid, &mut egid, &mut sgid))?;
+        }
+
+        if [ruid, euid, suid].iter().any(|id| *id != self.uid) {
+            return Err(failed("uid not changed"));
+        }
+
+        if [rgid, egid, sgid].iter().any(|id| *id != self.gid) {
+            return Err(failed("gid not changed"));
+        }
+
+        if unsafe { libc::getgroups(0, ptr::null_mut()) } != 0 {
+            return Err(failed("supplementary groups remain"));
+        }
+
+        let data = capget()?;
+        let caps = |f: fn(&CapData) -> u32| u64::from(f(&data[0])) | u64::from(f(&data[1])) << 32;
+
+        if caps(|d| d.permitted) != self.keep || caps(|d| d.effective) != self.keep {
+            return Err(failed("capabilities not dropped"));
+        }
+
+        // with no id left at 0, root can only be regained through CAP_SETUID
+        if self.uid != 0 && caps(|d| d.permitted) & (1 << CAP_SETUID) != 0 {
+            return Err(failed("able to regain root"));
+        }
+
+        Ok(())
+    }
+}
+
+fn capget() -> Result<[CapData; 2]> {
+    let mut header = CapHeader { version: CAP_VERSION_3, pid: 0 };
+    let mut data   = [CapData::default(); 2];
+    unsafe {
+        check(libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) as c_int)?;
+    }
+    Ok(data)
+}
+
+fn capset(data: &[CapData; 2]) -> Result<()> {
+    let mut header = CapHeader { version: CAP_VERSION_3, pid: 0 };
+    unsafe {
+        check(libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) as c_int)
+    }
+}
+
+fn check(rc: c_int) -> Result<()> {
+    match rc {
+        0 => Ok(()),
+        _ => Err(Error::last_os_error()),
+    }
+}
+
+fn failed(msg: &str) -> Error {
+    Error::new(ErrorKind::PermissionDenied, format!("privilege drop failed: {}", msg))
+}
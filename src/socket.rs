@@ -1,12 +1,20 @@
 // Copyright (C) 2020 - Will Glozer. All rights reserved.
 
 use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Read, Result, Write};
-use std::mem::{size_of, transmute, zeroed};
-use std::net::{SocketAddr, ToSocketAddrs};
-use std::os::unix::io::{AsRawFd, RawFd};
-use libc::{AF_INET, AF_INET6, c_int, msghdr, sockaddr_storage, socklen_t};
+use std::mem::{size_of, zeroed};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use libc::{AF_INET6, c_int, msghdr, sockaddr_in6, sockaddr_storage, socklen_t};
 use socket2::{Socket, SockAddr};
 use crate::{Domain, Type, Protocol};
+use crate::addr::{FromSockAddr, ToSockAddr};
+use crate::control::CMsg;
+#[cfg(target_os = "linux")]
+use crate::icmp;
+#[cfg(target_os = "linux")]
+use crate::iface::Interface;
+#[cfg(target_os = "linux")]
+use crate::netns::Netns;
 use crate::option::{Level, Name, Opt};
 
 pub struct RawSocket {
@@ -19,17 +27,51 @@ impl RawSocket {
         Ok(Self { sys })
     }
 
-    pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> Result<()> {
-        self.sys.bind(&sockaddr(addr)?)
+    /// Open an ICMP socket for `domain`, falling back to an unprivileged
+    /// datagram socket without `CAP_NET_RAW`. Failures carry an
+    /// `icmp::Error` explaining why neither kind could be opened.
+    #[cfg(target_os = "linux")]
+    pub fn new_icmp(domain: Domain) -> Result<(Self, icmp::Mode)> {
+        Ok(icmp::open(domain)?)
+    }
+
+    /// Open a socket in another network namespace. The socket remains
+    /// bound to that namespace when used from the calling thread.
+    #[cfg(target_os = "linux")]
+    pub fn new_in_netns<N: Into<Netns>>(
+        netns: N,
+        domain: Domain,
+        kind: Type,
+        protocol: Option<Protocol>,
+    ) -> Result<Self> {
+        netns.into().run(move || Self::new(domain, kind, protocol))
+    }
+
+    /// Create a new handle to the same socket by duplicating its descriptor.
+    pub fn try_clone(&self) -> Result<Self> {
+        let sys = self.sys.try_clone()?;
+        Ok(Self { sys })
+    }
+
+    pub fn bind<A: ToSockAddr>(&self, addr: A) -> Result<()> {
+        self.sys.bind(&addr.to_sockaddr()?)
     }
 
     pub fn local_addr(&self) -> Result<SocketAddr> {
-        socketaddr(&self.sys.local_addr()?)
+        self.local_addr_as()
+    }
+
+    pub fn local_addr_as<A: FromSockAddr>(&self) -> Result<A> {
+        A::from_sockaddr(&self.sys.local_addr()?)
     }
 
     pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.recv_from_as(buf)
+    }
+
+    pub fn recv_from_as<A: FromSockAddr>(&self, buf: &mut [u8]) -> Result<(usize, A)> {
         let (n, addr) = self.sys.recv_from(buf)?;
-        Ok((n, socketaddr(&addr)?))
+        Ok((n, A::from_sockaddr(&addr)?))
     }
 
     pub fn recv_msg(
@@ -37,10 +79,69 @@ impl RawSocket {
         data: &[IoSliceMut<'_>],
         ctrl: &mut [u8]
     ) -> Result<(usize, SocketAddr)> {
+        self.recv_msg_as(data, ctrl)
+    }
+
+    pub fn recv_msg_as<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8]
+    ) -> Result<(usize, A)> {
+        self.recv_msg_flags(data, ctrl, 0)
+    }
+
+    /// Receive a message with `recvmsg` flags, such as `MSG_PEEK`. Any
+    /// descriptors received with `SCM_RIGHTS` are closed.
+    pub fn recv_msg_flags<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+        flags: c_int,
+    ) -> Result<(usize, A)> {
+        let (n, addr, ..) = self.recv_msg_fds_flags(data, ctrl, flags, None)?;
+        Ok((n, addr))
+    }
+
+    /// Receive a message along with ownership of any descriptors passed
+    /// with `SCM_RIGHTS`, which are made close-on-exec where supported.
+    pub fn recv_msg_fds<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A, Vec<OwnedFd>)> {
+        let mut fds = Vec::new();
+        let flags   = crate::ffi::MSG_CMSG_CLOEXEC;
+        let (n, addr, ..) = self.recv_msg_fds_flags(data, ctrl, flags, Some(&mut fds))?;
+        Ok((n, addr, fds))
+    }
+
+    /// Receive a whole datagram, failing with `InvalidData` if it did not
+    /// fit in `data`. Returns the length of the control messages received
+    /// along with the length of the datagram and its sender.
+    #[cfg(feature = "async-tokio")]
+    pub(crate) fn recv_datagram<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A, usize)> {
+        let (n, addr, len, flags) = self.recv_msg_fds_flags(data, ctrl, 0, None)?;
+        match flags & libc::MSG_TRUNC {
+            0 => Ok((n, addr, len)),
+            _ => Err(Error::new(ErrorKind::InvalidData, "datagram truncated")),
+        }
+    }
+
+    fn recv_msg_fds_flags<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+        flags: c_int,
+        fds: Option<&mut Vec<OwnedFd>>,
+    ) -> Result<(usize, A, usize, c_int)> {
         let fd = self.as_raw_fd();
         unsafe {
             let mut addr: sockaddr_storage = zeroed();
-            let addr    = &mut addr as *mut _;
+            let addr    = &mut addr as *mut sockaddr_storage;
             let addrlen = size_of::<sockaddr_storage>();
 
             let mut msg: msghdr = zeroed();
@@ -54,35 +155,99 @@ impl RawSocket {
                 msg.msg_controllen = ctrl.len()    as      _;
             }
 
-            let n = match libc::recvmsg(fd, &mut msg, 0) {
+            let n = match libc::recvmsg(fd, &mut msg, flags) {
                 n if n >= 0 => n as usize,
                 _           => Err(Error::last_os_error())?,
             };
 
+            let controllen = msg.msg_controllen as usize;
+
+            let received = CMsg::take_fds(&mut ctrl[..controllen]);
+            if let Some(fds) = fds {
+                fds.extend(received);
+            }
+
+            if (*addr).ss_family as c_int == AF_INET6 {
+                let ctrl = &ctrl[..controllen];
+                let sin6 = &mut *(addr as *mut sockaddr_in6);
+                let ip   = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+                sin6.sin6_scope_id = link_scope(&ip, sin6.sin6_scope_id, ctrl);
+            }
+
             let addr = msg.msg_name as *const _;
             let len  = msg.msg_namelen;
-            let addr = socketaddr(&SockAddr::from_raw_parts(addr, len))?;
+            let addr = A::from_sockaddr(&SockAddr::from_raw_parts(addr, len))?;
 
-            Ok((n, addr))
+            Ok((n, addr, controllen, msg.msg_flags))
         }
     }
 
+    /// Receive an entry from the error queue, which is enabled by options
+    /// such as `IPV4_RECVERR`, `IPV6_RECVERR`, and `SO_TIMESTAMPING`.
+    #[cfg(target_os = "linux")]
+    pub fn recv_errqueue<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A)> {
+        self.recv_msg_flags(data, ctrl, libc::MSG_ERRQUEUE)
+    }
+
     pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         self.sys.read(buf)
     }
 
-    pub fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> Result<usize> {
-        self.sys.send_to(buf, &sockaddr(addr)?)
+    pub fn send_to<A: ToSockAddr>(&self, buf: &[u8], addr: A) -> Result<usize> {
+        self.send_to_addr(buf, &addr.to_sockaddr()?)
     }
 
-    pub fn send_msg<A: ToSocketAddrs>(
+    pub(crate) fn send_to_addr(&self, buf: &[u8], addr: &SockAddr) -> Result<usize> {
+        self.sys.send_to(buf, addr)
+    }
+
+    pub fn send_msg<A: ToSockAddr>(
         &self,
         addr: A,
         data: &[IoSlice<'_>],
         ctrl: &[u8],
     ) -> Result<usize> {
-        let fd   = self.as_raw_fd();
-        let addr = sockaddr(addr)?;
+        self.send_msg_addr(&addr.to_sockaddr()?, data, ctrl)
+    }
+
+    /// Send a message with `sendmsg` flags, such as `MSG_ZEROCOPY`.
+    pub fn send_msg_flags<A: ToSockAddr>(
+        &self,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: &[u8],
+        flags: c_int,
+    ) -> Result<usize> {
+        self.send_msg_addr_flags(&addr.to_sockaddr()?, data, ctrl, flags)
+    }
+
+    pub(crate) fn send_msg_addr(
+        &self,
+        addr: &SockAddr,
+        data: &[IoSlice<'_>],
+        ctrl: &[u8],
+    ) -> Result<usize> {
+        self.send_msg_addr_flags(addr, data, ctrl, 0)
+    }
+
+    pub(crate) fn send_msg_addr_flags(
+        &self,
+        addr: &SockAddr,
+        data: &[IoSlice<'_>],
+        ctrl: &[u8],
+        flags: c_int,
+    ) -> Result<usize> {
+        let fd = self.as_raw_fd();
+
+        let scoped = addr.as_inet6().filter(|addr| addr.scope_id() == 0).map(|mut addr| {
+            addr.set_scope_id(link_scope(addr.ip(), 0, ctrl));
+            SockAddr::from(addr)
+        });
+        let addr = scoped.as_ref().unwrap_or(addr);
 
         unsafe {
             let mut msg: msghdr = zeroed();
@@ -96,7 +261,7 @@ impl RawSocket {
                 msg.msg_controllen = ctrl.len()    as      _;
             }
 
-            match libc::sendmsg(fd, &msg, 0) {
+            match libc::sendmsg(fd, &msg, flags) {
                 n if n >= 0 => Ok(n as usize),
                 _           => Err(Error::last_os_error()),
             }
@@ -121,8 +286,8 @@ impl RawSocket {
         let len = &mut len as *mut _;
 
         unsafe {
-            let level = transmute(level);
-            let name  = transmute(name);
+            let level = level.into();
+            let name  = name.into();
             match libc::getsockopt(fd, level, name, ptr, len) {
                 0 => Ok(val),
                 _ => Err(Error::last_os_error()),
@@ -136,8 +301,8 @@ impl RawSocket {
         let len = size_of::<O>() as socklen_t;
 
         unsafe {
-            let level = transmute(level);
-            let name  = transmute(name);
+            let level = level.into();
+            let name  = name.into();
             match libc::setsockopt(fd, level, name, ptr, len) {
                 0 => Ok(()),
                 _ => Err(Error::last_os_error()),
@@ -148,6 +313,72 @@ impl RawSocket {
     pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
         self.sys.set_nonblocking(nonblocking)
     }
+
+    pub fn join_multicast_v4(&self, addr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.sys.join_multicast_v4(addr, interface)
+    }
+
+    pub fn join_multicast_v6(&self, addr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.sys.join_multicast_v6(addr, interface)
+    }
+
+    pub fn leave_multicast_v4(&self, addr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.sys.leave_multicast_v4(addr, interface)
+    }
+
+    pub fn leave_multicast_v6(&self, addr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.sys.leave_multicast_v6(addr, interface)
+    }
+
+    /// Only send and receive packets on the named interface, or on any
+    /// interface when `None`.
+    #[cfg(target_os = "linux")]
+    pub fn bind_device(&self, interface: Option<&str>) -> Result<()> {
+        let name = interface.map(std::ffi::CString::new).transpose();
+        let name = name.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        self.sys.bind_device(name.as_deref())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_ifindex(&self, ifindex: u32) -> Result<()> {
+        self.set_sockopt(Level::SOCKET, Name::SO_BINDTOIFINDEX, &(ifindex as libc::c_int))
+    }
+
+    /// Bind to `interface` by index, falling back to its name on kernels
+    /// without `SO_BINDTOIFINDEX`.
+    #[cfg(target_os = "linux")]
+    pub fn bind_interface(&self, interface: &Interface) -> Result<()> {
+        match self.bind_ifindex(interface.index()) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOPROTOOPT) => {
+                self.bind_device(Some(interface.name()))
+            },
+            result => result,
+        }
+    }
+
+    /// Name of the interface this socket is bound to, if any.
+    #[cfg(target_os = "linux")]
+    pub fn device(&self) -> Result<Option<String>> {
+        let name: [u8; libc::IFNAMSIZ] = self.get_sockopt(Level::SOCKET, Name::SO_BINDTODEVICE)?;
+        let name = name.split(|b| *b == 0).next().unwrap_or(&[]);
+        Ok(Some(String::from_utf8_lossy(name).into_owned()).filter(|name| !name.is_empty()))
+    }
+}
+
+/// Scope of a link-local IPv6 address, taken from the interface index of
+/// an `IPV6_PKTINFO` control message when not already known.
+fn link_scope(addr: &Ipv6Addr, scope_id: u32, ctrl: &[u8]) -> u32 {
+    let prefix = addr.segments()[0];
+    let link   = prefix & 0xffc0 == 0xfe80 || prefix & 0xff00 == 0xff00 && matches!(prefix & 0xf, 1 | 2);
+
+    if scope_id != 0 || !link {
+        return scope_id;
+    }
+
+    CMsg::decode(ctrl).find_map(|msg| match msg {
+        CMsg::Ipv6PktInfo(info) => Some(info.ifindex()),
+        _                       => None,
+    }).unwrap_or(0)
 }
 
 impl AsRawFd for RawSocket {
@@ -156,17 +387,62 @@ impl AsRawFd for RawSocket {
     }
 }
 
-fn sockaddr<A: ToSocketAddrs>(addr: A) -> Result<SockAddr> {
-    match addr.to_socket_addrs()?.next() {
-        Some(addr) => Ok(SockAddr::from(addr)),
-        None       => Err(Error::new(ErrorKind::InvalidInput, "invalid socket address")),
+/// Readiness source for a `mio` event loop. Set the socket non-blocking
+/// before registering it.
+#[cfg(feature = "mio")]
+impl mio::event::Source for RawSocket {
+    fn register(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> Result<()> {
+        mio::unix::SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> Result<()> {
+        mio::unix::SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> Result<()> {
+        mio::unix::SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
+impl AsFd for RawSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+impl FromRawFd for RawSocket {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        let sys = Socket::from_raw_fd(fd);
+        Self { sys }
+    }
+}
+
+impl IntoRawFd for RawSocket {
+    fn into_raw_fd(self) -> RawFd {
+        self.sys.into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for RawSocket {
+    fn from(fd: OwnedFd) -> Self {
+        unsafe { Self::from_raw_fd(fd.into_raw_fd()) }
+    }
+}
+
+impl From<RawSocket> for OwnedFd {
+    fn from(sock: RawSocket) -> Self {
+        unsafe { OwnedFd::from_raw_fd(sock.into_raw_fd()) }
+    }
+}
+
+impl From<Socket> for RawSocket {
+    fn from(sys: Socket) -> Self {
+        Self { sys }
     }
 }
 
-fn socketaddr(addr: &SockAddr) -> Result<SocketAddr> {
-    match addr.family() as c_int {
-        AF_INET  => Ok(addr.as_inet().expect("AF_INET addr").into()),
-        AF_INET6 => Ok(addr.as_inet6().expect("AF_INET6 addr").into()),
-        _        => Err(Error::new(ErrorKind::Other, "unknown address type")),
+impl From<RawSocket> for Socket {
+    fn from(sock: RawSocket) -> Self {
+        sock.sys
     }
 }
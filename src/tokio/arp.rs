@@ -0,0 +1,200 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::time::{Instant, timeout_at};
+use crate::addr::{LinkAddr, MacAddr};
+use crate::packet::{Arp, Ethernet};
+use crate::{Domain, Type};
+use super::RawSocket;
+
+pub struct ArpScanner {
+    sock:     RawSocket,
+    ifindex:  u32,
+    mac:      MacAddr,
+    ip:       Ipv4Addr,
+    interval: Duration,
+    timeout:  Duration,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ArpReply {
+    pub ip:   Ipv4Addr,
+    pub mac:  MacAddr,
+    pub time: Instant,
+    pub rtt:  Duration,
+}
+
+pub struct ArpResponder {
+    sock:    RawSocket,
+    ifindex: u32,
+    table:   HashMap<Ipv4Addr, MacAddr>,
+}
+
+impl ArpScanner {
+    pub fn new(ifindex: u32, mac: MacAddr, ip: Ipv4Addr) -> Result<Self> {
+        Ok(Self {
+            sock:     socket(ifindex)?,
+            ifindex,
+            mac,
+            ip,
+            interval: Duration::from_millis(1),
+            timeout:  Duration::from_secs(1),
+        })
+    }
+
+    /// Delay between consecutive requests.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Time to wait for replies after the last request is sent.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Probe every host in `net`/`prefix`. Prefixes shorter than /16 are
+    /// rejected with `InvalidInput` rather than flooding the link.
+    pub async fn scan(&self, net: Ipv4Addr, prefix: u8) -> Result<Vec<ArpReply>> {
+        let mut targets = hosts(net, prefix)?.filter(|ip| *ip != self.ip).peekable();
+        let mut sent    = HashMap::new();
+        let mut replies = Vec::<ArpReply>::new();
+
+        let mut next     = Instant::now();
+        let mut deadline = next;
+
+        let mut buf = [0u8; 64];
+
+        loop {
+            let wake = match targets.peek() {
+                Some(_) => next,
+                None    => deadline,
+            };
+
+            let (n, from) = match timeout_at(wake, self.sock.recv_from_as::<LinkAddr>(&mut buf)).await {
+                Ok(result) => result?,
+                Err(_)     => match targets.next() {
+                    Some(ip) => {
+                        sent.insert(ip, self.request(ip).await?);
+                        next     += self.interval;
+                        deadline  = Instant::now() + self.timeout;
+                        continue;
+                    }
+                    None => return Ok(replies),
+                },
+            };
+
+            let time = Instant::now();
+
+            let arp = match Arp::decode(&buf[..n]) {
+                Ok(arp) if arp.is_reply() && arp.tpa == self.ip => arp,
+                _                                               => continue,
+            };
+
+            if from.pkttype() == LinkAddr::OUTGOING || replies.iter().any(|r| r.ip == arp.spa) {
+                continue;
+            }
+
+            if let Some(start) = sent.get(&arp.spa) {
+                let ip  = arp.spa;
+                let mac = arp.sha;
+                let rtt = time - *start;
+                replies.push(ArpReply { ip, mac, time, rtt });
+            }
+        }
+    }
+
+    async fn request(&self, ip: Ipv4Addr) -> Result<Instant> {
+        let arp = Arp::request(self.mac, self.ip, ip);
+        send(&self.sock, self.ifindex, &arp).await?;
+        Ok(Instant::now())
+    }
+}
+
+impl ArpResponder {
+    pub fn new(ifindex: u32) -> Result<Self> {
+        let sock  = socket(ifindex)?;
+        let table = HashMap::new();
+        Ok(Self { sock, ifindex, table })
+    }
+
+    pub fn with(mut self, ip: Ipv4Addr, mac: MacAddr) -> Self {
+        self.table.insert(ip, mac);
+        self
+    }
+
+    /// Send a gratuitous ARP for every configured address.
+    pub async fn announce(&self) -> Result<()> {
+        for (ip, mac) in &self.table {
+            send(&self.sock, self.ifindex, &Arp::gratuitous(*mac, *ip)).await?;
+        }
+        Ok(())
+    }
+
+    /// Answer requests for configured addresses until an error occurs.
+    pub async fn run(&self) -> Result<()> {
+        loop {
+            self.respond().await?;
+        }
+    }
+
+    /// Wait for a request for a configured address and answer it.
+    pub async fn respond(&self) -> Result<Arp> {
+        let mut buf = [0u8; 64];
+
+        loop {
+            let (n, from) = self.sock.recv_from_as::<LinkAddr>(&mut buf).await?;
+
+            if from.pkttype() == LinkAddr::OUTGOING {
+                continue;
+            }
+
+            let req = match Arp::decode(&buf[..n]) {
+                Ok(arp) if arp.is_request() && !arp.is_gratuitous() => arp,
+                _                                                    => continue,
+            };
+
+            if let Some(mac) = self.table.get(&req.tpa) {
+                let reply = Arp::reply(*mac, req.tpa, req.sha, req.spa);
+                send(&self.sock, self.ifindex, &reply).await?;
+                return Ok(reply);
+            }
+        }
+    }
+}
+
+fn socket(ifindex: u32) -> Result<RawSocket> {
+    let domain = Domain::from(crate::ffi::AF_PACKET);
+    let sock   = crate::RawSocket::new(domain, Type::dgram(), None)?;
+    sock.bind(LinkAddr::new(Ethernet::ARP, ifindex))?;
+    RawSocket::from_sys(sock)
+}
+
+async fn send(sock: &RawSocket, ifindex: u32, arp: &Arp) -> Result<()> {
+    let mut buf = [0u8; Arp::SIZE];
+    let pkt = arp.encode(&mut buf).expect("ARP buffer size");
+    let dst = LinkAddr::new(Ethernet::ARP, ifindex).with_mac(arp.dst());
+    sock.send_to(pkt, dst).await?;
+    Ok(())
+}
+
+fn hosts(net: Ipv4Addr, prefix: u8) -> Result<impl Iterator<Item = Ipv4Addr>> {
+    if !(16..=32).contains(&prefix) {
+        return Err(Error::new(ErrorKind::InvalidInput, "ARP scan prefix must be /16 to /32"));
+    }
+
+    let mask  = u32::MAX << (32 - u32::from(prefix));
+    let first = u32::from(net) & mask;
+    let last  = first | !mask;
+
+    let range = match prefix {
+        31 | 32 => first..=last,
+        _       => first + 1..=last - 1,
+    };
+
+    Ok(range.map(Ipv4Addr::from))
+}
@@ -0,0 +1,163 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::io::{self, IoSliceMut};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use bytes::BytesMut;
+use futures::{ready, Sink, Stream};
+use tokio_util::codec::{Decoder, Encoder};
+use crate::control::CMsg;
+use crate::tokio::RawSocket;
+
+/// Datagram framing over a `RawSocket`. Each received datagram is decoded
+/// into frames tagged with the sender's address, and each frame sent is
+/// encoded into its own datagram. A datagram too large to receive whole is
+/// reported as an `InvalidData` error.
+pub struct RawFramed<C> {
+    sock:     RawSocket,
+    codec:    C,
+    rd:       BytesMut,
+    wr:       BytesMut,
+    ctrl:     Vec<u8>,
+    ctrl_len: usize,
+    addr:     Option<SocketAddr>,
+    out_addr: Option<SocketAddr>,
+    readable: bool,
+    flushed:  bool,
+}
+
+const INITIAL_RD_CAPACITY: usize = 64 * 1024;
+const INITIAL_WR_CAPACITY: usize = 8 * 1024;
+
+impl<C> RawFramed<C> {
+    pub fn new(sock: RawSocket, codec: C) -> Self {
+        Self {
+            sock,
+            codec,
+            rd:       BytesMut::with_capacity(INITIAL_RD_CAPACITY),
+            wr:       BytesMut::with_capacity(INITIAL_WR_CAPACITY),
+            ctrl:     Vec::new(),
+            ctrl_len: 0,
+            addr:     None,
+            out_addr: None,
+            readable: false,
+            flushed:  true,
+        }
+    }
+
+    /// Receive up to `len` bytes of control messages with each datagram,
+    /// available from `ctrl` while its frames are decoded.
+    pub fn with_ctrl(mut self, len: usize) -> Self {
+        self.ctrl = vec![0; len];
+        self
+    }
+
+    /// Control messages received with the datagram of the last frame.
+    pub fn ctrl(&self) -> impl Iterator<Item = CMsg<'_>> {
+        let ctrl = match self.addr {
+            Some(..) => &self.ctrl[..self.ctrl_len],
+            None     => &[],
+        };
+        CMsg::decode(ctrl)
+    }
+
+    pub fn get_ref(&self) -> &RawSocket {
+        &self.sock
+    }
+
+    pub fn get_mut(&mut self) -> &mut RawSocket {
+        &mut self.sock
+    }
+
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.rd
+    }
+
+    pub fn into_inner(self) -> RawSocket {
+        self.sock
+    }
+}
+
+impl<C: Decoder + Unpin> Stream for RawFramed<C> {
+    type Item = Result<(C::Item, SocketAddr), C::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.readable {
+                if let Some(frame) = this.codec.decode_eof(&mut this.rd)? {
+                    let addr = this.addr.expect("frame address");
+                    return Poll::Ready(Some(Ok((frame, addr))));
+                }
+                this.readable = false;
+                this.rd.clear();
+            }
+
+            this.rd.clear();
+            this.rd.resize(INITIAL_RD_CAPACITY, 0);
+
+            let (n, addr, ctrl_len) = {
+                let data = &[IoSliceMut::new(&mut this.rd)];
+                ready!(this.sock.poll_recv_datagram(cx, data, &mut this.ctrl))?
+            };
+
+            this.rd.truncate(n);
+            this.ctrl_len = ctrl_len;
+            this.addr     = Some(addr);
+            this.readable = true;
+        }
+    }
+}
+
+impl<I, C: Encoder<I> + Unpin> Sink<(I, SocketAddr)> for RawFramed<C> {
+    type Error = C::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.flushed {
+            true  => Poll::Ready(Ok(())),
+            false => self.poll_flush(cx),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, (frame, addr): (I, SocketAddr)) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.codec.encode(frame, &mut this.wr)?;
+        this.out_addr = Some(addr);
+        this.flushed  = false;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        if this.flushed {
+            return Poll::Ready(Ok(()));
+        }
+
+        let addr = this.out_addr.expect("frame address");
+        let n    = ready!(this.sock.poll_send_to(cx, &this.wr, addr))?;
+        let len  = this.wr.len();
+
+        this.wr.clear();
+        this.flushed = true;
+
+        match n == len {
+            true  => Poll::Ready(Ok(())),
+            false => Poll::Ready(Err(io::Error::other("failed to send entire datagram").into())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
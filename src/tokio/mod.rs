@@ -1,7 +1,17 @@
 // Copyright (C) 2020 - Will Glozer. All rights reserved.
 
+pub use self::framed::RawFramed;
 pub use self::socket::RawSocket;
 
+#[cfg(target_os = "linux")]
+pub mod arp;
+pub mod framed;
+pub mod ndp;
+#[cfg(target_os = "linux")]
+pub mod netlink;
 pub mod prelude;
+pub mod split;
+#[cfg(target_os = "linux")]
+pub mod zerocopy;
 
 mod socket;
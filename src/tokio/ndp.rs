@@ -0,0 +1,201 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{IoSlice, IoSliceMut, Result};
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::time::Duration;
+use libc::c_int;
+use tokio::time::{Instant, timeout_at};
+use crate::addr::MacAddr;
+use crate::control::{CMsg, Ipv6PktInfo};
+use crate::option::{Level, Name};
+use crate::packet::ndp::{self, NeighborSolicit, RouterAdvert};
+use crate::packet::{Ndp, NdpOption};
+use crate::{Domain, Protocol, Type};
+use super::RawSocket;
+
+pub struct NdpSocket {
+    sock:    RawSocket,
+    ifindex: u32,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Neighbor {
+    pub addr:   Ipv6Addr,
+    pub mac:    Option<MacAddr>,
+    pub router: bool,
+    pub rtt:    Duration,
+}
+
+pub struct RouterAdvertiser {
+    sock:     NdpSocket,
+    interval: Duration,
+}
+
+impl NdpSocket {
+    pub fn new(ifindex: u32) -> Result<Self> {
+        let sock = RawSocket::new(Domain::ipv6(), Type::raw(), Some(Protocol::icmpv6()))?;
+
+        let enable:  c_int = 1;
+        let disable: c_int = 0;
+        sock.set_sockopt(Level::IPV6, Name::IPV6_RECVHOPLIMIT,   &enable)?;
+        sock.set_sockopt(Level::IPV6, Name::IPV6_RECVPKTINFO,    &enable)?;
+        sock.set_sockopt(Level::IPV6, Name::IPV6_MULTICAST_LOOP, &disable)?;
+
+        Ok(Self { sock, ifindex })
+    }
+
+    pub fn ifindex(&self) -> u32 {
+        self.ifindex
+    }
+
+    pub fn join_multicast(&self, addr: &Ipv6Addr) -> Result<()> {
+        self.sock.join_multicast_v6(addr, self.ifindex)
+    }
+
+    /// Send `msg` to `dst` out of this socket's interface with the hop
+    /// limit set to 255.
+    pub async fn send(&self, dst: Ipv6Addr, msg: &Ndp<'_>) -> Result<()> {
+        let mut buf = [0u8; 1280];
+        let pkt = msg.encode(&mut buf)?;
+
+        let info  = Ipv6PktInfo::new(Ipv6Addr::UNSPECIFIED, self.ifindex);
+        let limit = CMsg::Ipv6HopLimit(ndp::HOP_LIMIT.into());
+
+        let mut ctrl = [0u8; 128];
+        let ctrl = CMsg::encode(&mut ctrl, &[limit, info.into()])?;
+
+        let dst = SocketAddrV6::new(dst, 0, 0, self.ifindex);
+        self.sock.send_msg(dst, &[IoSlice::new(pkt)], Some(ctrl)).await?;
+
+        Ok(())
+    }
+
+    /// Receive the next valid NDP message arriving on this socket's
+    /// interface, discarding anything not sent with a hop limit of 255.
+    pub async fn recv<'b>(&self, buf: &'b mut [u8]) -> Result<(Ndp<'b>, Ipv6Addr)> {
+        let (n, from) = loop {
+            let mut ctrl = [0u8; 128];
+
+            let iovec = &[IoSliceMut::new(buf)];
+            let (n, from) = self.sock.recv_msg(iovec, Some(&mut ctrl)).await?;
+
+            let from = match from {
+                SocketAddr::V6(from) => *from.ip(),
+                SocketAddr::V4(..)   => continue,
+            };
+
+            let (mut limit, mut ifindex) = (None, None);
+            for msg in CMsg::decode(&ctrl) {
+                match msg {
+                    CMsg::Ipv6HopLimit(n)   => limit   = Some(n),
+                    CMsg::Ipv6PktInfo(info) => ifindex = Some(info.ifindex()),
+                    _                       => (),
+                }
+            }
+
+            let valid = limit == Some(ndp::HOP_LIMIT.into()) && ifindex == Some(self.ifindex);
+
+            if valid && Ndp::decode(&buf[..n]).is_ok() {
+                break (n, from);
+            }
+        };
+
+        Ok((Ndp::decode(&buf[..n])?, from))
+    }
+
+    /// Solicit `target` and wait up to `timeout` for its advertisement.
+    pub async fn probe(&self, target: Ipv6Addr, mac: MacAddr, timeout: Duration) -> Result<Option<Neighbor>> {
+        let mut opts = [0u8; 8];
+        let opts = NdpOption::encode(&mut opts, &[NdpOption::SourceLinkAddr(mac)]).expect("option size");
+
+        let ns = Ndp::NeighborSolicit(NeighborSolicit::new(target, opts));
+
+        let start    = Instant::now();
+        let deadline = start + timeout;
+
+        self.send(ndp::solicited_node(&target), &ns).await?;
+
+        let mut buf = [0u8; 1280];
+
+        loop {
+            let na = match timeout_at(deadline, self.recv(&mut buf)).await {
+                Ok(result) => result?.0,
+                Err(_)     => return Ok(None),
+            };
+
+            if let Ndp::NeighborAdvert(na) = na {
+                if na.target != target {
+                    continue;
+                }
+
+                let mac = NdpOption::decode(na.options).find_map(|opt| match opt {
+                    NdpOption::TargetLinkAddr(mac) => Some(mac),
+                    _                              => None,
+                });
+
+                let addr   = target;
+                let router = na.router;
+                let rtt    = start.elapsed();
+
+                return Ok(Some(Neighbor { addr, mac, router, rtt }));
+            }
+        }
+    }
+}
+
+impl RouterAdvertiser {
+    pub fn new(ifindex: u32) -> Result<Self> {
+        let sock = NdpSocket::new(ifindex)?;
+        sock.join_multicast(&ndp::ALL_ROUTERS)?;
+
+        let interval = Duration::from_secs(200);
+
+        Ok(Self { sock, interval })
+    }
+
+    /// Interval between unsolicited advertisements.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Send `ra` to all nodes immediately, then every interval, until an
+    /// error occurs. Router solicitations pull the next advertisement
+    /// forward by a random delay of up to `MAX_RA_DELAY_TIME`, but never
+    /// closer than `MIN_DELAY_BETWEEN_RAS` to the previous one, as
+    /// required by RFC 4861 section 6.2.6.
+    pub async fn run(&self, ra: &RouterAdvert<'_>) -> Result<()> {
+        let ra = Ndp::RouterAdvert(*ra);
+
+        let mut next = Instant::now();
+        let mut last = None;
+        let mut buf  = [0u8; 1280];
+
+        loop {
+            if let Ok(result) = timeout_at(next, self.sock.recv(&mut buf)).await {
+                if let Ndp::RouterSolicit(..) = result?.0 {
+                    let mut at = Instant::now() + ra_delay();
+                    if let Some(last) = last {
+                        at = at.max(last + ndp::MIN_DELAY_BETWEEN_RAS);
+                    }
+                    next = next.min(at);
+                }
+                continue;
+            }
+
+            self.sock.send(ndp::ALL_NODES, &ra).await?;
+
+            let now = Instant::now();
+            last = Some(now);
+            next = now + self.interval;
+        }
+    }
+}
+
+fn ra_delay() -> Duration {
+    let max  = ndp::MAX_RA_DELAY_TIME.as_millis() as u64;
+    let seed = RandomState::new().build_hasher().finish();
+    Duration::from_millis(seed % (max + 1))
+}
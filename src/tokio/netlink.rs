@@ -0,0 +1,122 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::collections::VecDeque;
+use std::io::Result;
+use std::net::IpAddr;
+use futures::stream::{self, Stream};
+use libc::c_int;
+use crate::addr::NetlinkAddr;
+use crate::netlink::route::{Address, Event, Link, Neighbor, Query, Route};
+use crate::netlink::{BUFFER_SIZE, NETLINK_ROUTE, NlMsg, Transaction};
+use crate::option::{Level, Name};
+use crate::packet::Error;
+use super::RawSocket;
+
+/// Async rtnetlink query handle, serialized like the blocking
+/// [`Rtnetlink`](crate::netlink::route::Rtnetlink).
+pub struct Rtnetlink {
+    sock: RawSocket,
+    seq:  u32,
+}
+
+pub fn socket(protocol: c_int) -> Result<RawSocket> {
+    RawSocket::from_sys(crate::netlink::socket(protocol)?)
+}
+
+/// Send `msg` to the kernel and pass each reply to `f` until the request
+/// is complete. Multicast notifications received meanwhile are dropped.
+pub async fn request<F>(sock: &RawSocket, msg: &NlMsg<'_>, mut f: F) -> Result<()>
+where
+    F: FnMut(NlMsg<'_>) -> Result<()>,
+{
+    let mut buf = vec![0u8; BUFFER_SIZE.max(msg.size())];
+
+    let pkt = msg.encode(&mut buf)?;
+    sock.send_to(pkt, NetlinkAddr::kernel()).await?;
+
+    let tx = Transaction::new(msg);
+
+    loop {
+        let (n, from) = sock.recv_from_as::<NetlinkAddr>(&mut buf).await?;
+        if from.groups() != 0 {
+            continue;
+        }
+
+        if tx.handle(&buf[..n], &mut f)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Stream of link, address, route, and neighbor changes published to the
+/// given `RTNLGRP_*` multicast groups.
+pub fn watch(groups: &[u32]) -> Result<impl Stream<Item = Result<Event>>> {
+    let sock = crate::netlink::socket(NETLINK_ROUTE)?;
+    sock.bind(NetlinkAddr::new(0, 0))?;
+
+    for group in groups {
+        sock.set_sockopt(Level::NETLINK, Name::NETLINK_ADD_MEMBERSHIP, &(*group as c_int))?;
+    }
+
+    let sock = RawSocket::from_sys(sock)?;
+
+    let buf    = vec![0u8; BUFFER_SIZE];
+    let events = VecDeque::new();
+
+    Ok(stream::unfold((sock, buf, events), |(sock, mut buf, mut events)| async move {
+        while events.is_empty() {
+            let n = match sock.recv_from_as::<NetlinkAddr>(&mut buf).await {
+                Ok((n, _)) => n,
+                Err(e)     => return Some((Err(e), (sock, buf, events))),
+            };
+
+            let result: std::result::Result<(), Error> = NlMsg::decode(&buf[..n]).try_for_each(|msg| {
+                events.extend(Event::parse(&msg?));
+                Ok(())
+            });
+
+            if let Err(e) = result {
+                return Some((Err(e.into()), (sock, buf, events)));
+            }
+        }
+
+        let event = events.pop_front().map(Ok)?;
+        Some((event, (sock, buf, events)))
+    }))
+}
+
+impl Rtnetlink {
+    pub fn new() -> Result<Self> {
+        let sock = socket(NETLINK_ROUTE)?;
+        Ok(Self { sock, seq: 0 })
+    }
+
+    pub async fn links(&mut self) -> Result<Vec<Link>> {
+        self.query(Query::links()).await
+    }
+
+    pub async fn addresses(&mut self) -> Result<Vec<Address>> {
+        self.query(Query::addresses()).await
+    }
+
+    pub async fn routes(&mut self) -> Result<Vec<Route>> {
+        self.query(Query::routes()).await
+    }
+
+    pub async fn neighbors(&mut self) -> Result<Vec<Neighbor>> {
+        self.query(Query::neighbors()).await
+    }
+
+    /// Look up the route the kernel would use to reach `dst`.
+    pub async fn route_to(&mut self, dst: IpAddr) -> Result<Route> {
+        let routes = self.query(Query::route_to(dst)?).await?;
+        Query::first(routes)
+    }
+
+    async fn query<T>(&mut self, query: Query<T>) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let msg = query.msg(&mut self.seq);
+        request(&self.sock, &msg, query.collect(&mut items)).await?;
+        Ok(items)
+    }
+}
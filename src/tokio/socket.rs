@@ -1,15 +1,26 @@
 // Copyright (C) 2020 - Will Glozer. All rights reserved.
 
+use crate::addr::{FromSockAddr, ToSockAddr};
+#[cfg(target_os = "linux")]
+use crate::errqueue;
+#[cfg(target_os = "linux")]
+use crate::icmp;
+#[cfg(target_os = "linux")]
+use crate::iface::Interface;
+#[cfg(target_os = "linux")]
+use crate::netns::Netns;
 use crate::option::{Level, Name, Opt};
+use crate::tokio::split::{self, OwnedRecvHalf, OwnedSendHalf, RecvHalf, SendHalf};
 use crate::{Domain, Protocol, Type};
 use futures::ready;
+use std::convert::TryFrom;
 use std::io::{self, IoSlice, IoSliceMut, Result};
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::os::unix::prelude::*;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::unix::AsyncFd;
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncWrite, Interest, ReadBuf, Ready};
 
 pub struct RawSocket {
     io: AsyncFd<crate::RawSocket>,
@@ -17,13 +28,42 @@ pub struct RawSocket {
 
 impl RawSocket {
     pub fn new(domain: Domain, kind: Type, protocol: Option<Protocol>) -> Result<Self> {
-        let sys = crate::RawSocket::new(domain, kind, protocol)?;
+        Self::from_sys(crate::RawSocket::new(domain, kind, protocol)?)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_in_netns<N: Into<Netns>>(
+        netns: N,
+        domain: Domain,
+        kind: Type,
+        protocol: Option<Protocol>,
+    ) -> Result<Self> {
+        Self::from_sys(crate::RawSocket::new_in_netns(netns, domain, kind, protocol)?)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_icmp(domain: Domain) -> Result<(Self, icmp::Mode)> {
+        let (sys, mode) = crate::RawSocket::new_icmp(domain)?;
+        Ok((Self::from_sys(sys)?, mode))
+    }
+
+    pub(crate) fn from_sys(sys: crate::RawSocket) -> Result<Self> {
         sys.set_nonblocking(true)?;
-        let io = AsyncFd::new(sys)?;
+        // sys owns its descriptor for as long as the AsyncFd owns sys
+        let io = unsafe { AsyncFd::register(sys)? };
         Ok(RawSocket { io })
     }
 
-    pub async fn bind<A: ToSocketAddrs>(&self, addr: A) -> Result<()> {
+    pub(crate) fn sys(&self) -> &crate::RawSocket {
+        self.io.get_ref()
+    }
+
+    /// Create a new handle to the same socket by duplicating its descriptor.
+    pub fn try_clone(&self) -> Result<Self> {
+        Self::from_sys(self.io.get_ref().try_clone()?)
+    }
+
+    pub async fn bind<A: ToSockAddr>(&self, addr: A) -> Result<()> {
         self.io.get_ref().bind(addr)
     }
 
@@ -31,8 +71,16 @@ impl RawSocket {
         self.io.get_ref().local_addr()
     }
 
+    pub fn local_addr_as<A: FromSockAddr>(&self) -> Result<A> {
+        self.io.get_ref().local_addr_as()
+    }
+
     pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
-        self.read(|s| s.recv_from(buf)).await
+        self.recv_from_as(buf).await
+    }
+
+    pub async fn recv_from_as<A: FromSockAddr>(&self, buf: &mut [u8]) -> Result<(usize, A)> {
+        self.read(|s| s.recv_from_as(buf)).await
     }
 
     pub async fn recv_msg(
@@ -40,22 +88,282 @@ impl RawSocket {
         data: &[IoSliceMut<'_>],
         ctrl: Option<&mut [u8]>,
     ) -> Result<(usize, SocketAddr)> {
+        self.recv_msg_as(data, ctrl).await
+    }
+
+    pub async fn recv_msg_as<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Result<(usize, A)> {
         let ctrl = ctrl.unwrap_or(&mut []);
-        self.read(|s| s.recv_msg(data, ctrl)).await
+        self.read(|s| s.recv_msg_as(data, ctrl)).await
+    }
+
+    pub async fn recv_msg_flags<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+        flags: libc::c_int,
+    ) -> Result<(usize, A)> {
+        let ctrl = ctrl.unwrap_or(&mut []);
+        self.read(|s| s.recv_msg_flags(data, ctrl, flags)).await
+    }
+
+    /// Receive a message along with ownership of any descriptors passed
+    /// with `SCM_RIGHTS`.
+    pub async fn recv_msg_fds<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A, Vec<OwnedFd>)> {
+        self.read(|s| s.recv_msg_fds(data, ctrl)).await
     }
 
-    pub async fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> Result<usize> {
-        self.write(|s| s.send_to(buf, &addr)).await
+    /// Receive an entry from the error queue, waiting for error readiness
+    /// since queued errors do not make the socket readable.
+    #[cfg(target_os = "linux")]
+    pub async fn recv_errqueue<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A)> {
+        loop {
+            let mut guard = self.io.ready(Interest::ERROR).await?;
+            match guard.try_io(|inner| inner.get_ref().recv_errqueue(data, ctrl)) {
+                Ok(r) => return r,
+                Err(_) => continue,
+            }
+        }
     }
 
-    pub async fn send_msg<A: ToSocketAddrs>(
+    /// Stream of error queue entries, with up to `len` bytes of each
+    /// original packet. The stream ends after yielding any error but an
+    /// interrupted receive.
+    #[cfg(target_os = "linux")]
+    pub fn errqueue(&self, len: usize) -> impl futures::Stream<Item = Result<errqueue::Message>> + '_ {
+        futures::stream::unfold(Some(self), move |sock| async move {
+            let sock = sock?;
+
+            let mut data = vec![0u8; len];
+            let mut ctrl = [0u8; 256];
+
+            let iovec  = &[IoSliceMut::new(&mut data)];
+            let result = sock.recv_errqueue::<socket2::SockAddr>(iovec, &mut ctrl).await;
+            let result = result.map(|(n, addr)| errqueue::Message::parse(&data[..n], &ctrl, &addr));
+
+            let next = match &result {
+                Err(e) if e.kind() != io::ErrorKind::Interrupted => None,
+                _                                                => Some(sock),
+            };
+
+            Some((result, next))
+        })
+    }
+
+    pub async fn send_to<A: ToSockAddr>(&self, buf: &[u8], addr: A) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        self.write(|s| s.send_to_addr(buf, &addr)).await
+    }
+
+    pub async fn send_msg<A: ToSockAddr>(
         &self,
         addr: A,
         data: &[IoSlice<'_>],
         ctrl: Option<&[u8]>,
     ) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
         let ctrl = ctrl.unwrap_or(&[]);
-        self.write(|s| s.send_msg(&addr, data, ctrl)).await
+        self.write(|s| s.send_msg_addr(&addr, data, ctrl)).await
+    }
+
+    pub async fn send_msg_flags<A: ToSockAddr>(
+        &self,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: Option<&[u8]>,
+        flags: libc::c_int,
+    ) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        let ctrl = ctrl.unwrap_or(&[]);
+        self.write(|s| s.send_msg_addr_flags(&addr, data, ctrl, flags)).await
+    }
+
+    /// Wait for any of the readiness events in `interest`.
+    pub async fn ready(&self, interest: Interest) -> Result<Ready> {
+        Ok(self.io.ready(interest).await?.ready())
+    }
+
+    /// Wait until the socket may be readable. A following `try_recv_*` can
+    /// still fail with `WouldBlock`.
+    pub async fn readable(&self) -> Result<()> {
+        self.ready(Interest::READABLE).await.map(|_| ())
+    }
+
+    /// Wait until the socket may be writable. A following `try_send_*` can
+    /// still fail with `WouldBlock`.
+    pub async fn writable(&self) -> Result<()> {
+        self.ready(Interest::WRITABLE).await.map(|_| ())
+    }
+
+    pub fn poll_recv_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.io.poll_read_ready(cx).map_ok(|_| ())
+    }
+
+    pub fn poll_send_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.io.poll_write_ready(cx).map_ok(|_| ())
+    }
+
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<SocketAddr>> {
+        self.poll_recv_from_as(cx, buf)
+    }
+
+    pub fn poll_recv_from_as<A: FromSockAddr>(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<A>> {
+        let (n, addr) = ready!(self.poll_read(cx, |s| s.recv_from_as(unfilled(buf))))?;
+        unsafe { buf.assume_init(n) };
+        buf.advance(n);
+        Poll::Ready(Ok(addr))
+    }
+
+    pub fn poll_recv_msg(
+        &self,
+        cx: &mut Context<'_>,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Poll<Result<(usize, SocketAddr)>> {
+        self.poll_recv_msg_as(cx, data, ctrl)
+    }
+
+    pub fn poll_recv_msg_as<A: FromSockAddr>(
+        &self,
+        cx: &mut Context<'_>,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Poll<Result<(usize, A)>> {
+        let ctrl = ctrl.unwrap_or(&mut []);
+        self.poll_read(cx, |s| s.recv_msg_as(data, ctrl))
+    }
+
+    /// Poll for a whole datagram, as received by `recv_datagram`.
+    pub(crate) fn poll_recv_datagram(
+        &self,
+        cx: &mut Context<'_>,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Poll<Result<(usize, SocketAddr, usize)>> {
+        self.poll_read(cx, |s| s.recv_datagram(data, ctrl))
+    }
+
+    pub fn poll_send_to<A: ToSockAddr>(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        addr: A,
+    ) -> Poll<Result<usize>> {
+        let addr = addr.to_sockaddr()?;
+        self.poll_write(cx, |s| s.send_to_addr(buf, &addr))
+    }
+
+    pub fn poll_send_msg<A: ToSockAddr>(
+        &self,
+        cx: &mut Context<'_>,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: Option<&[u8]>,
+    ) -> Poll<Result<usize>> {
+        let addr = addr.to_sockaddr()?;
+        let ctrl = ctrl.unwrap_or(&[]);
+        self.poll_write(cx, |s| s.send_msg_addr(&addr, data, ctrl))
+    }
+
+    pub fn try_recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.try_recv_from_as(buf)
+    }
+
+    pub fn try_recv_from_as<A: FromSockAddr>(&self, buf: &mut [u8]) -> Result<(usize, A)> {
+        self.io.try_io(Interest::READABLE, |s| s.recv_from_as(buf))
+    }
+
+    pub fn try_recv_msg(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Result<(usize, SocketAddr)> {
+        self.try_recv_msg_as(data, ctrl)
+    }
+
+    pub fn try_recv_msg_as<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: Option<&mut [u8]>,
+    ) -> Result<(usize, A)> {
+        let ctrl = ctrl.unwrap_or(&mut []);
+        self.io.try_io(Interest::READABLE, |s| s.recv_msg_as(data, ctrl))
+    }
+
+    pub fn try_send_to<A: ToSockAddr>(&self, buf: &[u8], addr: A) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        self.io.try_io(Interest::WRITABLE, |s| s.send_to_addr(buf, &addr))
+    }
+
+    pub fn try_send_msg<A: ToSockAddr>(
+        &self,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: Option<&[u8]>,
+    ) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        let ctrl = ctrl.unwrap_or(&[]);
+        self.io.try_io(Interest::WRITABLE, |s| s.send_msg_addr(&addr, data, ctrl))
+    }
+
+    pub fn try_send_msg_flags<A: ToSockAddr>(
+        &self,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: Option<&[u8]>,
+        flags: libc::c_int,
+    ) -> Result<usize> {
+        let ctrl = ctrl.unwrap_or(&[]);
+        self.try_send_msg_addr_flags(&addr.to_sockaddr()?, data, ctrl, flags)
+    }
+
+    pub(crate) fn try_send_msg_addr_flags(
+        &self,
+        addr: &socket2::SockAddr,
+        data: &[IoSlice<'_>],
+        ctrl: &[u8],
+        flags: libc::c_int,
+    ) -> Result<usize> {
+        self.io.try_io(Interest::WRITABLE, |s| s.send_msg_addr_flags(addr, data, ctrl, flags))
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn try_recv_errqueue<A: FromSockAddr>(
+        &self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8],
+    ) -> Result<(usize, A)> {
+        self.io.try_io(Interest::ERROR, |s| s.recv_errqueue(data, ctrl))
+    }
+
+    /// Borrow separate receive and send halves of this socket.
+    pub fn split(&self) -> (RecvHalf<'_>, SendHalf<'_>) {
+        split::split(self)
+    }
+
+    /// Split into owned halves that can move to separate tasks and later
+    /// be rejoined with `reunite`.
+    pub fn into_split(self) -> (OwnedRecvHalf, OwnedSendHalf) {
+        split::into_split(self)
     }
 
     pub fn get_sockopt<O: Opt>(&self, level: Level, name: Name) -> Result<O> {
@@ -66,6 +374,42 @@ impl RawSocket {
         self.io.get_ref().set_sockopt(level, name, value)
     }
 
+    pub fn join_multicast_v4(&self, addr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.io.get_ref().join_multicast_v4(addr, interface)
+    }
+
+    pub fn join_multicast_v6(&self, addr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.io.get_ref().join_multicast_v6(addr, interface)
+    }
+
+    pub fn leave_multicast_v4(&self, addr: &Ipv4Addr, interface: &Ipv4Addr) -> Result<()> {
+        self.io.get_ref().leave_multicast_v4(addr, interface)
+    }
+
+    pub fn leave_multicast_v6(&self, addr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.io.get_ref().leave_multicast_v6(addr, interface)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_device(&self, interface: Option<&str>) -> Result<()> {
+        self.io.get_ref().bind_device(interface)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_ifindex(&self, ifindex: u32) -> Result<()> {
+        self.io.get_ref().bind_ifindex(ifindex)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_interface(&self, interface: &Interface) -> Result<()> {
+        self.io.get_ref().bind_interface(interface)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn device(&self) -> Result<Option<String>> {
+        self.io.get_ref().device()
+    }
+
     async fn read<F: FnMut(&crate::RawSocket) -> Result<R>, R>(&self, mut f: F) -> Result<R> {
         loop {
             let mut guard = self.io.readable().await?;
@@ -85,6 +429,39 @@ impl RawSocket {
             }
         }
     }
+
+    fn poll_read<F: FnMut(&crate::RawSocket) -> Result<R>, R>(
+        &self,
+        cx: &mut Context<'_>,
+        mut f: F,
+    ) -> Poll<Result<R>> {
+        loop {
+            let mut guard = ready!(self.io.poll_read_ready(cx))?;
+            match guard.try_io(|inner| f(inner.get_ref())) {
+                Ok(r) => return Poll::Ready(r),
+                Err(_) => continue,
+            }
+        }
+    }
+
+    fn poll_write<F: FnMut(&crate::RawSocket) -> Result<R>, R>(
+        &self,
+        cx: &mut Context<'_>,
+        mut f: F,
+    ) -> Poll<Result<R>> {
+        loop {
+            let mut guard = ready!(self.io.poll_write_ready(cx))?;
+            match guard.try_io(|inner| f(inner.get_ref())) {
+                Ok(r) => return Poll::Ready(r),
+                Err(_) => continue,
+            }
+        }
+    }
+}
+
+fn unfilled<'a>(buf: &'a mut ReadBuf<'_>) -> &'a mut [u8] {
+    let b = unsafe { buf.unfilled_mut() };
+    unsafe { &mut *(b as *mut [std::mem::MaybeUninit<u8>] as *mut [u8]) }
 }
 
 impl AsyncWrite for RawSocket {
@@ -123,16 +500,12 @@ impl AsyncRead for RawSocket {
             let mut guard = ready!(self.io.poll_read_ready_mut(cx))?;
 
             match guard.try_io(|inner| {
-                let b = unsafe { buf.unfilled_mut() };
-                let b = unsafe { std::mem::transmute::<_, &mut [u8]>(b) };
-
-                inner.get_mut().read(b)
+                inner.get_mut().read(unfilled(buf))
             }) {
                 Ok(result) => {
-                    let result = result.and_then(|size| {
+                    let result = result.map(|size| {
                         unsafe { buf.assume_init(size) };
                         buf.advance(size);
-                        Ok(())
                     });
                     return Poll::Ready(result);
                 }
@@ -147,3 +520,57 @@ impl AsRawFd for RawSocket {
         self.io.get_ref().as_raw_fd()
     }
 }
+
+impl AsFd for RawSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.io.get_ref().as_fd()
+    }
+}
+
+impl IntoRawFd for RawSocket {
+    fn into_raw_fd(self) -> RawFd {
+        self.io.into_inner().into_raw_fd()
+    }
+}
+
+impl TryFrom<crate::RawSocket> for RawSocket {
+    type Error = io::Error;
+
+    fn try_from(sys: crate::RawSocket) -> Result<Self> {
+        Self::from_sys(sys)
+    }
+}
+
+impl TryFrom<OwnedFd> for RawSocket {
+    type Error = io::Error;
+
+    fn try_from(fd: OwnedFd) -> Result<Self> {
+        Self::from_sys(fd.into())
+    }
+}
+
+impl TryFrom<socket2::Socket> for RawSocket {
+    type Error = io::Error;
+
+    fn try_from(sys: socket2::Socket) -> Result<Self> {
+        Self::from_sys(sys.into())
+    }
+}
+
+impl From<RawSocket> for crate::RawSocket {
+    fn from(sock: RawSocket) -> Self {
+        sock.io.into_inner()
+    }
+}
+
+impl From<RawSocket> for OwnedFd {
+    fn from(sock: RawSocket) -> Self {
+        sock.io.into_inner().into()
+    }
+}
+
+impl From<RawSocket> for socket2::Socket {
+    fn from(sock: RawSocket) -> Self {
+        sock.io.into_inner().into()
+    }
+}
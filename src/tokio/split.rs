@@ -0,0 +1,271 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{IoSlice, IoSliceMut, Result};
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use libc::c_int;
+use tokio::io::ReadBuf;
+use crate::addr::{FromSockAddr, ToSockAddr};
+use crate::tokio::RawSocket;
+
+/// Receiving half of a `RawSocket`, created by `split`.
+pub struct RecvHalf<'a>(&'a RawSocket);
+
+/// Sending half of a `RawSocket`, created by `split`.
+pub struct SendHalf<'a>(&'a RawSocket);
+
+/// Receiving half of a `RawSocket`, created by `into_split`.
+pub struct OwnedRecvHalf(Arc<RawSocket>);
+
+/// Sending half of a `RawSocket`, created by `into_split`.
+pub struct OwnedSendHalf(Arc<RawSocket>);
+
+/// Halves passed to `reunite` that came from different sockets.
+pub struct ReuniteError(pub OwnedRecvHalf, pub OwnedSendHalf);
+
+pub(crate) fn split(sock: &RawSocket) -> (RecvHalf<'_>, SendHalf<'_>) {
+    (RecvHalf(sock), SendHalf(sock))
+}
+
+pub(crate) fn into_split(sock: RawSocket) -> (OwnedRecvHalf, OwnedSendHalf) {
+    let sock = Arc::new(sock);
+    (OwnedRecvHalf(sock.clone()), OwnedSendHalf(sock))
+}
+
+pub(crate) fn reunite(recv: OwnedRecvHalf, send: OwnedSendHalf) -> std::result::Result<RawSocket, ReuniteError> {
+    if !Arc::ptr_eq(&recv.0, &send.0) {
+        return Err(ReuniteError(recv, send));
+    }
+
+    drop(send);
+    match Arc::try_unwrap(recv.0) {
+        Ok(sock) => Ok(sock),
+        Err(_)   => unreachable!("RawSocket halves are the only owners"),
+    }
+}
+
+// Forward the receiving and sending methods of each half to the socket,
+// borrowed or shared.
+macro_rules! recv_half {
+    ($half:ty) => {
+        impl $half {
+            pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+                self.0.recv_from(buf).await
+            }
+
+            pub async fn recv_from_as<A: FromSockAddr>(&self, buf: &mut [u8]) -> Result<(usize, A)> {
+                self.0.recv_from_as(buf).await
+            }
+
+            pub async fn recv_msg(
+                &self,
+                data: &[IoSliceMut<'_>],
+                ctrl: Option<&mut [u8]>,
+            ) -> Result<(usize, SocketAddr)> {
+                self.0.recv_msg(data, ctrl).await
+            }
+
+            pub async fn recv_msg_as<A: FromSockAddr>(
+                &self,
+                data: &[IoSliceMut<'_>],
+                ctrl: Option<&mut [u8]>,
+            ) -> Result<(usize, A)> {
+                self.0.recv_msg_as(data, ctrl).await
+            }
+
+            pub async fn recv_msg_flags<A: FromSockAddr>(
+                &self,
+                data: &[IoSliceMut<'_>],
+                ctrl: Option<&mut [u8]>,
+                flags: c_int,
+            ) -> Result<(usize, A)> {
+                self.0.recv_msg_flags(data, ctrl, flags).await
+            }
+
+            pub async fn recv_msg_fds<A: FromSockAddr>(
+                &self,
+                data: &[IoSliceMut<'_>],
+                ctrl: &mut [u8],
+            ) -> Result<(usize, A, Vec<OwnedFd>)> {
+                self.0.recv_msg_fds(data, ctrl).await
+            }
+
+            pub fn try_recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+                self.0.try_recv_from(buf)
+            }
+
+            pub fn try_recv_msg(
+                &self,
+                data: &[IoSliceMut<'_>],
+                ctrl: Option<&mut [u8]>,
+            ) -> Result<(usize, SocketAddr)> {
+                self.0.try_recv_msg(data, ctrl)
+            }
+
+            pub fn poll_recv_from(
+                &self,
+                cx: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<Result<SocketAddr>> {
+                self.0.poll_recv_from(cx, buf)
+            }
+
+            pub fn poll_recv_msg(
+                &self,
+                cx: &mut Context<'_>,
+                data: &[IoSliceMut<'_>],
+                ctrl: Option<&mut [u8]>,
+            ) -> Poll<Result<(usize, SocketAddr)>> {
+                self.0.poll_recv_msg(cx, data, ctrl)
+            }
+
+            pub fn poll_recv_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+                self.0.poll_recv_ready(cx)
+            }
+
+            pub async fn readable(&self) -> Result<()> {
+                self.0.readable().await
+            }
+
+            pub fn local_addr(&self) -> Result<SocketAddr> {
+                self.0.local_addr()
+            }
+        }
+    };
+}
+
+macro_rules! send_half {
+    ($half:ty) => {
+        impl $half {
+            pub async fn send_to<A: ToSockAddr>(&self, buf: &[u8], addr: A) -> Result<usize> {
+                self.0.send_to(buf, addr).await
+            }
+
+            pub async fn send_msg<A: ToSockAddr>(
+                &self,
+                addr: A,
+                data: &[IoSlice<'_>],
+                ctrl: Option<&[u8]>,
+            ) -> Result<usize> {
+                self.0.send_msg(addr, data, ctrl).await
+            }
+
+            pub fn try_send_to<A: ToSockAddr>(&self, buf: &[u8], addr: A) -> Result<usize> {
+                self.0.try_send_to(buf, addr)
+            }
+
+            pub fn try_send_msg<A: ToSockAddr>(
+                &self,
+                addr: A,
+                data: &[IoSlice<'_>],
+                ctrl: Option<&[u8]>,
+            ) -> Result<usize> {
+                self.0.try_send_msg(addr, data, ctrl)
+            }
+
+            pub fn poll_send_to<A: ToSockAddr>(
+                &self,
+                cx: &mut Context<'_>,
+                buf: &[u8],
+                addr: A,
+            ) -> Poll<Result<usize>> {
+                self.0.poll_send_to(cx, buf, addr)
+            }
+
+            pub fn poll_send_msg<A: ToSockAddr>(
+                &self,
+                cx: &mut Context<'_>,
+                addr: A,
+                data: &[IoSlice<'_>],
+                ctrl: Option<&[u8]>,
+            ) -> Poll<Result<usize>> {
+                self.0.poll_send_msg(cx, addr, data, ctrl)
+            }
+
+            pub fn poll_send_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+                self.0.poll_send_ready(cx)
+            }
+
+            pub async fn writable(&self) -> Result<()> {
+                self.0.writable().await
+            }
+
+            pub fn local_addr(&self) -> Result<SocketAddr> {
+                self.0.local_addr()
+            }
+        }
+    };
+}
+
+recv_half!(RecvHalf<'_>);
+recv_half!(OwnedRecvHalf);
+send_half!(SendHalf<'_>);
+send_half!(OwnedSendHalf);
+
+impl OwnedRecvHalf {
+    /// Rejoin with the `OwnedSendHalf` split from the same socket.
+    pub fn reunite(self, send: OwnedSendHalf) -> std::result::Result<RawSocket, ReuniteError> {
+        reunite(self, send)
+    }
+}
+
+impl OwnedSendHalf {
+    /// Rejoin with the `OwnedRecvHalf` split from the same socket.
+    pub fn reunite(self, recv: OwnedRecvHalf) -> std::result::Result<RawSocket, ReuniteError> {
+        reunite(recv, self)
+    }
+}
+
+impl AsRef<RawSocket> for RecvHalf<'_> {
+    fn as_ref(&self) -> &RawSocket {
+        self.0
+    }
+}
+
+impl AsRef<RawSocket> for SendHalf<'_> {
+    fn as_ref(&self) -> &RawSocket {
+        self.0
+    }
+}
+
+impl AsRef<RawSocket> for OwnedRecvHalf {
+    fn as_ref(&self) -> &RawSocket {
+        &self.0
+    }
+}
+
+impl AsRef<RawSocket> for OwnedSendHalf {
+    fn as_ref(&self) -> &RawSocket {
+        &self.0
+    }
+}
+
+impl AsRawFd for OwnedRecvHalf {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl AsRawFd for OwnedSendHalf {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReuniteError").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tried to reunite halves of different sockets")
+    }
+}
+
+impl Error for ReuniteError {}
@@ -0,0 +1,126 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::io::{ErrorKind, IoSlice, Result};
+use std::os::unix::io::AsRawFd;
+use std::sync::{Mutex, MutexGuard};
+use futures::lock::Mutex as AsyncMutex;
+use socket2::SockAddr;
+use crate::addr::ToSockAddr;
+use crate::ffi::MSG_ZEROCOPY;
+use crate::option::{Level, Name};
+use crate::tokio::RawSocket;
+use crate::zerocopy::{self, Token, Tracker};
+
+/// Zerocopy sends on a `RawSocket` with `SO_ZEROCOPY` enabled. Sends may
+/// run concurrently with tasks awaiting `released`, which resolves once
+/// the kernel no longer references a buffer. Dropping blocks the thread
+/// until the kernel releases every pending buffer, for at most
+/// [`FLUSH_TIMEOUT`](crate::zerocopy::FLUSH_TIMEOUT), so take them with
+/// `into_inner` to avoid stalling the runtime.
+pub struct ZeroCopy<B> {
+    sock:    Option<RawSocket>,
+    tracker: Mutex<Tracker<B>>,
+    reader:  AsyncMutex<()>,
+}
+
+impl<B: AsRef<[u8]>> ZeroCopy<B> {
+    pub fn new(sock: RawSocket) -> Result<Self> {
+        sock.set_sockopt(Level::SOCKET, Name::SO_ZEROCOPY, &1)?;
+        Ok(Self {
+            sock:    Some(sock),
+            tracker: Mutex::new(Tracker::new()),
+            reader:  AsyncMutex::new(()),
+        })
+    }
+
+    pub async fn send_to<A: ToSockAddr>(&self, buf: B, addr: A) -> Result<Token> {
+        self.send_msg(addr, buf, None).await
+    }
+
+    pub async fn send_msg<A: ToSockAddr>(&self, addr: A, buf: B, ctrl: Option<&[u8]>) -> Result<Token> {
+        let addr = addr.to_sockaddr()?;
+        let data = &[IoSlice::new(buf.as_ref())];
+        let ctrl = ctrl.unwrap_or(&[]);
+
+        let sock = self.sock();
+
+        loop {
+            sock.writable().await?;
+
+            // hold the tracker while sending so tokens follow the kernel's order
+            let mut tracker = self.tracker();
+            match sock.try_send_msg_addr_flags(&addr, data, ctrl, MSG_ZEROCOPY) {
+                Ok(..)                                          => return Ok(tracker.sent(buf)),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => continue,
+                Err(e)                                          => return Err(e),
+            }
+        }
+    }
+
+    /// Wait until the kernel releases the buffer of the send identified
+    /// by `token`.
+    pub async fn released(&self, token: Token) -> Result<B> {
+        loop {
+            if let Some(buf) = self.tracker().take(token)? {
+                return Ok(buf);
+            }
+
+            let _reader = self.reader.lock().await;
+
+            if let Some(buf) = self.tracker().take(token)? {
+                return Ok(buf);
+            }
+
+            let sock = self.sock();
+
+            let mut ctrl = [0u8; 128];
+            sock.recv_errqueue::<SockAddr>(&[], &mut ctrl).await?;
+
+            let mut tracker = self.tracker();
+            tracker.record(&ctrl);
+            zerocopy::drain(|ctrl| sock.try_recv_errqueue(&[], ctrl), &mut tracker)?;
+        }
+    }
+
+    /// Number of sends whose buffers the kernel may still reference.
+    pub fn pending(&self) -> usize {
+        self.tracker().pending()
+    }
+
+    /// Whether the kernel copied the data of the last completed sends.
+    pub fn copied(&self) -> bool {
+        self.tracker().copied()
+    }
+
+    pub fn get_ref(&self) -> &RawSocket {
+        self.sock()
+    }
+
+    /// Unwrap the socket along with the buffers of every send not yet
+    /// taken, including those the kernel may still reference.
+    pub fn into_inner(mut self) -> (RawSocket, Vec<(Token, B)>) {
+        let bufs = self.tracker().take_remaining();
+        (self.sock.take().expect("socket"), bufs)
+    }
+
+    fn sock(&self) -> &RawSocket {
+        self.sock.as_ref().expect("socket")
+    }
+
+    fn tracker(&self) -> MutexGuard<'_, Tracker<B>> {
+        self.tracker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<B> Drop for ZeroCopy<B> {
+    fn drop(&mut self) {
+        // taken by into_inner along with every buffer
+        let sock = match &self.sock {
+            Some(sock) => sock.sys(),
+            None       => return,
+        };
+        let recv    = |ctrl: &mut [u8]| sock.recv_errqueue::<SockAddr>(&[], ctrl);
+        let tracker = self.tracker.get_mut().unwrap_or_else(|e| e.into_inner());
+        zerocopy::flush(sock.as_raw_fd(), recv, tracker);
+    }
+}
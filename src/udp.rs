@@ -0,0 +1,16 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::slice::Chunks;
+use crate::control::CMsg;
+
+/// Split a buffer received with `UDP_GRO` enabled back into datagrams,
+/// each the segment size from its `UdpGro` control message except the
+/// last, which may be shorter. A buffer without the message holds one
+/// datagram.
+pub fn segments<'a>(data: &'a [u8], ctrl: &[u8]) -> Chunks<'a, u8> {
+    let size = CMsg::decode(ctrl).find_map(|msg| match msg {
+        CMsg::UdpGro(size) if size > 0 => Some(size as usize),
+        _                              => None,
+    });
+    data.chunks(size.unwrap_or(data.len()).max(1))
+}
@@ -0,0 +1,503 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::io::{Error, ErrorKind, IoSlice, IoSliceMut, Result};
+use std::mem::{size_of, zeroed};
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::ptr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use io_uring::{cqueue, opcode, squeue, types, IoUring};
+use libc::{iovec, msghdr, sockaddr_storage, ENOBUFS};
+use socket2::SockAddr;
+use crate::addr::{FromSockAddr, ToSockAddr};
+use crate::control::CMsg;
+use crate::RawSocket;
+
+/// Raw socket driven by an io_uring instance. Each call submits its
+/// operations and waits for them to complete, so batched sends and
+/// multishot receives cost one syscall for many packets.
+pub struct UringSocket {
+    ring:  IoUring,
+    sock:  RawSocket,
+    fixed: Vec<Vec<u8>>,
+    group: Option<BufRing>,
+}
+
+/// Ring of provided buffers the kernel selects from for multishot receives.
+struct BufRing {
+    ring:    *mut types::BufRingEntry,
+    entries: u16,
+    size:    usize,
+    tail:    u16,
+    bufs:    Vec<u64>,
+}
+
+const GROUP:     u16 = 0;
+const SINGLE:    u64 = u64::MAX;
+const MULTISHOT: u64 = u64::MAX - 1;
+const CANCEL:    u64 = u64::MAX - 2;
+
+impl UringSocket {
+    pub fn new(sock: RawSocket, entries: u32) -> Result<Self> {
+        let ring = IoUring::new(entries)?;
+        Ok(Self { ring, sock, fixed: Vec::new(), group: None })
+    }
+
+    pub fn get_ref(&self) -> &RawSocket {
+        &self.sock
+    }
+
+    pub fn into_inner(self) -> RawSocket {
+        self.sock
+    }
+
+    pub fn recv_msg(
+        &mut self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8]
+    ) -> Result<(usize, SocketAddr)> {
+        self.recv_msg_as(data, ctrl)
+    }
+
+    pub fn recv_msg_as<A: FromSockAddr>(
+        &mut self,
+        data: &[IoSliceMut<'_>],
+        ctrl: &mut [u8]
+    ) -> Result<(usize, A)> {
+        unsafe {
+            let mut addr: sockaddr_storage = zeroed();
+            let mut msg = header(&mut addr as *mut _ as *mut _, size_of::<sockaddr_storage>(), data.as_ptr() as *const _, data.len(), ctrl);
+
+            let sqe = opcode::RecvMsg::new(self.fd(), &mut msg).build();
+            let n   = self.run(sqe)?;
+
+            drop(CMsg::take_fds(&mut ctrl[..msg.msg_controllen as usize]));
+
+            let addr = SockAddr::from_raw_parts(msg.msg_name as *const _, msg.msg_namelen);
+            Ok((n, A::from_sockaddr(&addr)?))
+        }
+    }
+
+    pub fn send_msg<A: ToSockAddr>(
+        &mut self,
+        addr: A,
+        data: &[IoSlice<'_>],
+        ctrl: &[u8],
+    ) -> Result<usize> {
+        let addr = addr.to_sockaddr()?;
+        let msg  = header(addr.as_ptr() as *mut _, addr.len() as usize, data.as_ptr() as *const _, data.len(), ctrl);
+        let sqe  = opcode::SendMsg::new(self.fd(), &msg).build();
+        self.run(sqe)
+    }
+
+    /// Send each packet to its address, submitting as many as the ring
+    /// holds at once. Returns the number sent, or the first error after
+    /// all submitted sends complete.
+    pub fn send_batch<A: ToSockAddr, B: AsRef<[u8]>>(&mut self, msgs: &[(A, B)]) -> Result<usize> {
+        let addrs = msgs.iter().map(|(addr, _)| addr.to_sockaddr()).collect::<Result<Vec<_>>>()?;
+        let iovs  = msgs.iter().map(|(_, buf)| {
+            let buf = buf.as_ref();
+            iovec { iov_base: buf.as_ptr() as *mut _, iov_len: buf.len() }
+        }).collect::<Vec<_>>();
+        let hdrs  = addrs.iter().zip(&iovs).map(|(addr, iov)| {
+            header(addr.as_ptr() as *mut _, addr.len() as usize, iov, 1, &[])
+        }).collect::<Vec<_>>();
+
+        let fd    = self.fd();
+        let cap   = self.ring.submission().capacity();
+        let mut sent  = 0;
+        let mut error = None;
+
+        for (base, chunk) in hdrs.chunks(cap).enumerate() {
+            let mut pending = 0;
+            let pushed = chunk.iter().enumerate().try_for_each(|(index, msg)| {
+                let sqe = opcode::SendMsg::new(fd, msg).build().user_data((base * cap + index) as u64);
+                unsafe { push(&mut self.ring, &sqe)? };
+                pending += 1;
+                Ok(())
+            });
+
+            // with nothing queued there is nothing to cancel or reap
+            let pushed = match (pending, pushed) {
+                (0, Err(e)) => return Err(e),
+                (_, pushed) => pushed,
+            };
+
+            complete(&mut self.ring, pushed, |_, cqe| {
+                if cqe.user_data() == CANCEL {
+                    return Ok(true);
+                }
+
+                match cqe.result() {
+                    n if n >= 0 => sent += 1,
+                    n           => {
+                        error.get_or_insert_with(|| Error::from_raw_os_error(-n));
+                    },
+                }
+                pending -= 1;
+
+                Ok(pending > 0)
+            })?;
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None    => Ok(sent),
+        }
+    }
+
+    /// Register buffers with the kernel for use with `send_fixed`,
+    /// replacing any previously registered.
+    pub fn register_buffers(&mut self, bufs: Vec<Vec<u8>>) -> Result<()> {
+        let submitter = self.ring.submitter();
+
+        if !self.fixed.is_empty() {
+            submitter.unregister_buffers()?;
+            self.fixed.clear();
+        }
+
+        let iovs = bufs.iter().map(|buf| {
+            iovec { iov_base: buf.as_ptr() as *mut _, iov_len: buf.len() }
+        }).collect::<Vec<_>>();
+
+        unsafe { submitter.register_buffers(&iovs)? };
+        self.fixed = bufs;
+
+        Ok(())
+    }
+
+    pub fn buffer(&self, index: u16) -> Option<&[u8]> {
+        self.fixed.get(index as usize).map(Vec::as_slice)
+    }
+
+    pub fn buffer_mut(&mut self, index: u16) -> Option<&mut [u8]> {
+        self.fixed.get_mut(index as usize).map(Vec::as_mut_slice)
+    }
+
+    /// Send the first `len` bytes of registered buffer `index` without
+    /// copying, waiting until the kernel releases the buffer.
+    pub fn send_fixed<A: ToSockAddr>(&mut self, addr: A, index: u16, len: usize) -> Result<usize> {
+        let buf = match self.fixed.get(index as usize) {
+            Some(buf) if len <= buf.len() => buf,
+            _                             => return Err(Error::new(ErrorKind::InvalidInput, "invalid buffer")),
+        };
+
+        let addr = addr.to_sockaddr()?;
+        let sqe  = opcode::SendZc::new(self.fd(), buf.as_ptr(), len as u32)
+            .buf_index(Some(index))
+            .dest_addr(addr.as_ptr() as *const _)
+            .dest_addr_len(addr.len())
+            .build()
+            .user_data(SINGLE);
+
+        unsafe { push(&mut self.ring, &sqe)? };
+
+        let mut result = None;
+        complete(&mut self.ring, Ok(()), |_, cqe| {
+            if cqe.user_data() != SINGLE {
+                return Ok(true);
+            }
+
+            let flags = cqe.flags();
+            if cqueue::notif(flags) {
+                return Ok(false);
+            }
+
+            result = Some(cqe.result());
+            Ok(cqueue::more(flags))
+        })?;
+
+        match result {
+            Some(n) if n >= 0 => Ok(n as usize),
+            Some(n)           => Err(Error::from_raw_os_error(-n)),
+            None              => Err(Error::other("missing completion")),
+        }
+    }
+
+    /// Provide `entries` buffers of `size` bytes for multishot receives,
+    /// replacing any previously provided. `entries` must be a power of two.
+    pub fn provide_buffers(&mut self, entries: u16, size: usize) -> Result<()> {
+        if let Some(group) = self.group.take() {
+            self.ring.submitter().unregister_buf_ring(GROUP)?;
+            drop(group);
+        }
+
+        let group = BufRing::new(entries, size)?;
+        unsafe { self.ring.submitter().register_buf_ring_with_flags(group.ring as u64, entries, GROUP, 0)? };
+        self.group = Some(group);
+
+        Ok(())
+    }
+
+    /// Receive packets with a multishot `recvmsg` into the provided buffers,
+    /// calling `f` with the payload, source address, and up to `ctrl` bytes
+    /// of control messages of each until it returns false. Control data is
+    /// decoded with `CMsg::decode`.
+    pub fn recv_multishot<A, F>(&mut self, ctrl: usize, mut f: F) -> Result<()>
+    where
+        A: FromSockAddr,
+        F: FnMut(&[u8], A, &[u8]) -> bool,
+    {
+        let group = match self.group.as_mut() {
+            Some(group) => group,
+            None        => return Err(Error::new(ErrorKind::InvalidInput, "no provided buffers")),
+        };
+
+        let mut msg: msghdr = unsafe { zeroed() };
+        msg.msg_namelen    = size_of::<sockaddr_storage>() as _;
+        msg.msg_controllen = ((ctrl + 7) & !7) as _;
+
+        let fd  = self.sock.as_raw_fd();
+        let sqe = opcode::RecvMsgMulti::new(types::Fd(fd), &msg, GROUP).build().user_data(MULTISHOT);
+        unsafe { push(&mut self.ring, &sqe)? };
+
+        let mut stopping = false;
+        let mut result   = Ok(());
+
+        complete(&mut self.ring, Ok(()), |mut ring, cqe| {
+            if cqe.user_data() != MULTISHOT {
+                return Ok(true);
+            }
+
+            let (n, flags) = (cqe.result(), cqe.flags());
+            let mut cancel = false;
+
+            if let Some(bid) = cqueue::buffer_select(flags) {
+                let buf = group.buf(bid, n.max(0) as usize);
+                close_fds(buf, &msg);
+
+                if !stopping {
+                    let next = deliver(buf, &msg, &mut f);
+                    if !matches!(next, Ok(true)) {
+                        result   = next.map(|_| ());
+                        stopping = true;
+                        cancel   = cqueue::more(flags);
+                    }
+                }
+                group.recycle(bid);
+            }
+
+            if let (true, Some(ring)) = (cancel, ring.as_deref_mut()) {
+                let sqe = opcode::AsyncCancel::new(MULTISHOT).build().user_data(CANCEL);
+                unsafe { push(ring, &sqe)? };
+            }
+
+            if cqueue::more(flags) {
+                return Ok(true);
+            }
+
+            // the receive is no longer armed, so nothing is left in flight
+            match (-n, ring) {
+                _ if stopping                   => Ok(false),
+                (e, _) if e > 0 && e != ENOBUFS => {
+                    result = Err(Error::from_raw_os_error(e));
+                    Ok(false)
+                },
+                (_, Some(ring))                 => match unsafe { push(ring, &sqe) } {
+                    Ok(())  => Ok(true),
+                    Err(e)  => {
+                        result = Err(e);
+                        Ok(false)
+                    },
+                },
+                (_, None)                       => Ok(false),
+            }
+        })?;
+
+        result
+    }
+
+    fn fd(&self) -> types::Fd {
+        types::Fd(self.sock.as_raw_fd())
+    }
+
+    fn run(&mut self, sqe: squeue::Entry) -> Result<usize> {
+        let sqe = sqe.user_data(SINGLE);
+        unsafe { push(&mut self.ring, &sqe)? };
+
+        let mut result = None;
+        complete(&mut self.ring, Ok(()), |_, cqe| {
+            if cqe.user_data() == SINGLE {
+                result = Some(cqe.result());
+            }
+            Ok(result.is_none())
+        })?;
+
+        match result {
+            Some(n) if n >= 0 => Ok(n as usize),
+            Some(n)           => Err(Error::from_raw_os_error(-n)),
+            None              => Err(Error::other("missing completion")),
+        }
+    }
+}
+
+impl BufRing {
+    fn new(entries: u16, size: usize) -> Result<Self> {
+        if !entries.is_power_of_two() || size == 0 || size > u32::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid buffer ring"));
+        }
+
+        let len  = entries as usize * size_of::<types::BufRingEntry>();
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        let map  = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_POPULATE;
+        let ring = unsafe { libc::mmap(ptr::null_mut(), len, prot, map, -1, 0) };
+
+        if ring == libc::MAP_FAILED {
+            return Err(Error::last_os_error());
+        }
+
+        // keep each buffer 8-byte aligned so control messages decode in place
+        let size = (size + 7) & !7;
+        let ring = ring as *mut types::BufRingEntry;
+        let bufs = vec![0; entries as usize * size / 8];
+        let mut this = Self { ring, entries, size, tail: 0, bufs };
+
+        for bid in 0..entries {
+            this.recycle(bid);
+        }
+
+        Ok(this)
+    }
+
+    fn buf(&mut self, bid: u16, len: usize) -> &mut [u8] {
+        let start = bid as usize * self.size / 8;
+        let buf   = &mut self.bufs[start..start + self.size / 8];
+        unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, len.min(self.size)) }
+    }
+
+    fn recycle(&mut self, bid: u16) {
+        let index = self.tail & (self.entries - 1);
+        let addr  = self.bufs[bid as usize * self.size / 8..].as_ptr();
+
+        unsafe {
+            let entry = &mut *self.ring.add(index as usize);
+            entry.set_addr(addr as u64);
+            entry.set_len(self.size as u32);
+            entry.set_bid(bid);
+
+            self.tail = self.tail.wrapping_add(1);
+            let tail  = types::BufRingEntry::tail(self.ring) as *const AtomicU16;
+            (*tail).store(self.tail, Ordering::Release);
+        }
+    }
+}
+
+impl Drop for BufRing {
+    fn drop(&mut self) {
+        let len = self.entries as usize * size_of::<types::BufRingEntry>();
+        unsafe { libc::munmap(self.ring as *mut _, len) };
+    }
+}
+
+impl AsRawFd for UringSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+fn deliver<A, F>(buf: &[u8], msg: &msghdr, f: &mut F) -> Result<bool>
+where
+    A: FromSockAddr,
+    F: FnMut(&[u8], A, &[u8]) -> bool,
+{
+    let out = types::RecvMsgOut::parse(buf, msg).map_err(|_| {
+        Error::new(ErrorKind::InvalidData, "truncated multishot message")
+    })?;
+
+    let name = out.name_data();
+    let addr = unsafe { SockAddr::from_raw_parts(name.as_ptr() as *const _, name.len() as _) };
+    let addr = A::from_sockaddr(&addr)?;
+
+    Ok(f(out.payload_data(), addr, out.control_data()))
+}
+
+/// Close any descriptors passed with `SCM_RIGHTS` in a multishot message,
+/// which no caller can take ownership of.
+fn close_fds(buf: &mut [u8], msg: &msghdr) {
+    let ctrl = match types::RecvMsgOut::parse(buf, msg) {
+        Ok(out) => out.control_data().as_ptr_range(),
+        Err(_)  => return,
+    };
+
+    let start = ctrl.start as usize - buf.as_ptr() as usize;
+    let end   = ctrl.end   as usize - buf.as_ptr() as usize;
+    drop(unsafe { CMsg::take_fds(&mut buf[start..end]) });
+}
+
+fn header(name: *mut libc::c_void, namelen: usize, iov: *const iovec, iovlen: usize, ctrl: &[u8]) -> msghdr {
+    let mut msg: msghdr = unsafe { zeroed() };
+    msg.msg_name    = name;
+    msg.msg_namelen = namelen    as      _;
+    msg.msg_iov     = iov        as *mut _;
+    msg.msg_iovlen  = iovlen     as      _;
+
+    if !ctrl.is_empty() {
+        msg.msg_control    = ctrl.as_ptr() as *mut _;
+        msg.msg_controllen = ctrl.len()    as      _;
+    }
+
+    msg
+}
+
+/// Submit queued requests and pass each completion to `f` until it returns
+/// false, retrying when interrupted by a signal. When queueing requests
+/// failed, as reported by `pushed`, submitting fails, or `f` returns an error
+/// every request in flight is cancelled, and completions are still passed to
+/// `f`, without the ring to queue more, until it returns false so no request
+/// outlives the memory it references. The first error is returned once done,
+/// or as soon as the cancellation itself cannot be submitted.
+fn complete<F>(ring: &mut IoUring, pushed: Result<()>, mut f: F) -> Result<()>
+where
+    F: FnMut(Option<&mut IoUring>, &cqueue::Entry) -> Result<bool>,
+{
+    let mut error  = pushed.err();
+    let mut cancel = error.is_some();
+
+    loop {
+        if cancel {
+            let sqe = opcode::AsyncCancel2::new(types::CancelBuilder::any()).build().user_data(CANCEL);
+            if let Err(e) = unsafe { push(ring, &sqe) } {
+                return Err(error.unwrap_or(e));
+            }
+            cancel = false;
+        }
+
+        match ring.submit_and_wait(1) {
+            Err(e) if e.kind() != ErrorKind::Interrupted => match error {
+                // waiting fails even with the cancellation queued, so give up
+                Some(error) => return Err(error),
+                None        => {
+                    cancel = true;
+                    error  = Some(e);
+                },
+            },
+            _ => (),
+        }
+
+        let cqes = ring.completion().collect::<Vec<_>>();
+        for cqe in cqes {
+            let queue = match error {
+                None    => Some(&mut *ring),
+                Some(_) => None,
+            };
+
+            match f(queue, &cqe) {
+                Ok(true)  => (),
+                Ok(false) => return error.map_or(Ok(()), Err),
+                Err(e)    => {
+                    cancel |= error.is_none();
+                    error   = error.or(Some(e));
+                },
+            }
+        }
+    }
+}
+
+/// Queue `sqe`, submitting first if the queue is full. On error `sqe` is not
+/// queued, so returning early is safe when it is the only request.
+unsafe fn push(ring: &mut IoUring, sqe: &squeue::Entry) -> Result<()> {
+    if ring.submission().is_full() {
+        ring.submit()?;
+    }
+    ring.submission().push(sqe).map_err(|_| Error::other("submission queue full"))
+}
@@ -0,0 +1,255 @@
+// Copyright (C) 2020 - Will Glozer. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, IoSlice, Result};
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+use libc::{c_int, MSG_DONTWAIT, MSG_ERRQUEUE};
+use socket2::SockAddr;
+use crate::addr::ToSockAddr;
+use crate::control::{CMsg, ExtendedErr};
+use crate::ffi::MSG_ZEROCOPY;
+use crate::option::{Level, Name};
+use crate::RawSocket;
+
+/// Identifier of a zerocopy send. The kernel numbers each successful
+/// `MSG_ZEROCOPY` send on a socket, starting from zero.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Token(u32);
+
+/// Zerocopy sends on a socket with `SO_ZEROCOPY` enabled. Each buffer is
+/// held until an error queue entry reports that the kernel no longer
+/// references it, and other error queue entries are discarded. Dropping
+/// blocks until the kernel releases every pending buffer, or for at most
+/// `FLUSH_TIMEOUT`. Linux only supports `SO_ZEROCOPY` on TCP and UDP
+/// sockets.
+pub struct ZeroCopy<B> {
+    sock:    Option<RawSocket>,
+    tracker: Tracker<B>,
+}
+
+/// Longest a drop waits for the kernel to release pending buffers, which
+/// are leaked if it does not.
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Buffers of zerocopy sends, by token, until they are taken after the
+/// kernel releases them.
+pub(crate) struct Tracker<B> {
+    next:     u32,
+    pending:  BTreeMap<u32, B>,
+    released: BTreeMap<u32, B>,
+    copied:   bool,
+}
+
+impl Token {
+    pub fn id(self) -> u32 {
+        self.0
+    }
+}
+
+impl<B: AsRef<[u8]>> ZeroCopy<B> {
+    pub fn new(sock: RawSocket) -> Result<Self> {
+        sock.set_sockopt(Level::SOCKET, Name::SO_ZEROCOPY, &1)?;
+        Ok(Self { sock: Some(sock), tracker: Tracker::new() })
+    }
+
+    pub fn send_to<A: ToSockAddr>(&mut self, buf: B, addr: A) -> Result<Token> {
+        self.send_msg(addr, buf, &[])
+    }
+
+    pub fn send_msg<A: ToSockAddr>(&mut self, addr: A, buf: B, ctrl: &[u8]) -> Result<Token> {
+        let data = &[IoSlice::new(buf.as_ref())];
+        self.sock().send_msg_flags(addr, data, ctrl, MSG_ZEROCOPY)?;
+        Ok(self.tracker.sent(buf))
+    }
+
+    /// Read completions from the error queue without blocking, returning
+    /// the buffers the kernel has released.
+    pub fn complete(&mut self) -> Result<Vec<(Token, B)>> {
+        self.drain()?;
+        Ok(self.tracker.take_all())
+    }
+
+    /// Block until the kernel releases the buffer of the send identified
+    /// by `token`.
+    pub fn wait(&mut self, token: Token) -> Result<B> {
+        loop {
+            if let Some(buf) = self.tracker.take(token)? {
+                return Ok(buf);
+            }
+
+            poll(self.sock().as_raw_fd(), None)?;
+            self.drain()?;
+        }
+    }
+
+    /// Number of sends whose buffers the kernel may still reference.
+    pub fn pending(&self) -> usize {
+        self.tracker.pending()
+    }
+
+    /// Whether the kernel copied the data of the last completed sends.
+    pub fn copied(&self) -> bool {
+        self.tracker.copied()
+    }
+
+    pub fn get_ref(&self) -> &RawSocket {
+        self.sock()
+    }
+
+    /// Unwrap the socket along with the buffers of every send not yet
+    /// taken, including those the kernel may still reference.
+    pub fn into_inner(mut self) -> (RawSocket, Vec<(Token, B)>) {
+        let bufs = self.tracker.take_remaining();
+        (self.sock.take().expect("socket"), bufs)
+    }
+
+    fn sock(&self) -> &RawSocket {
+        self.sock.as_ref().expect("socket")
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        let sock = self.sock.as_ref().expect("socket");
+        drain(|ctrl| sock.recv_msg_flags::<SockAddr>(&[], ctrl, MSG_ERRQUEUE | MSG_DONTWAIT), &mut self.tracker)
+    }
+}
+
+impl<B> Drop for ZeroCopy<B> {
+    fn drop(&mut self) {
+        // taken by into_inner along with every buffer
+        let sock = match &self.sock {
+            Some(sock) => sock,
+            None       => return,
+        };
+        let recv = |ctrl: &mut [u8]| sock.recv_msg_flags::<SockAddr>(&[], ctrl, MSG_ERRQUEUE | MSG_DONTWAIT);
+        flush(sock.as_raw_fd(), recv, &mut self.tracker);
+    }
+}
+
+impl<B> Tracker<B> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next:     0,
+            pending:  BTreeMap::new(),
+            released: BTreeMap::new(),
+            copied:   false,
+        }
+    }
+
+    pub(crate) fn sent(&mut self, buf: B) -> Token {
+        let id = self.next;
+        self.next = id.wrapping_add(1);
+        self.pending.insert(id, buf);
+        Token(id)
+    }
+
+    /// Release the buffers of a zerocopy completion, ignoring other entries.
+    fn complete(&mut self, err: &ExtendedErr) {
+        let range = match err.zerocopy() {
+            Some(range) => range,
+            None        => return,
+        };
+
+        let (lo, hi) = range.into_inner();
+        for n in 0..=hi.wrapping_sub(lo) {
+            let id = lo.wrapping_add(n);
+            if let Some(buf) = self.pending.remove(&id) {
+                self.released.insert(id, buf);
+            }
+        }
+        self.copied = err.copied();
+    }
+
+    /// Complete sends from the control messages of an error queue entry.
+    pub(crate) fn record(&mut self, ctrl: &[u8]) {
+        for msg in CMsg::decode(ctrl) {
+            if let CMsg::ExtendedErr(err) = msg {
+                self.complete(&err);
+            }
+        }
+    }
+
+    pub(crate) fn take(&mut self, token: Token) -> Result<Option<B>> {
+        match self.released.remove(&token.0) {
+            Some(buf)                                  => Ok(Some(buf)),
+            None if self.pending.contains_key(&token.0) => Ok(None),
+            None                                       => Err(Error::new(ErrorKind::InvalidInput, "unknown zerocopy token")),
+        }
+    }
+
+    pub(crate) fn take_all(&mut self) -> Vec<(Token, B)> {
+        let released = mem::take(&mut self.released);
+        released.into_iter().map(|(id, buf)| (Token(id), buf)).collect()
+    }
+
+    /// Take the buffers of every send, released or still pending.
+    pub(crate) fn take_remaining(&mut self) -> Vec<(Token, B)> {
+        let mut bufs = self.take_all();
+        let pending  = mem::take(&mut self.pending);
+        bufs.extend(pending.into_iter().map(|(id, buf)| (Token(id), buf)));
+        bufs
+    }
+
+    pub(crate) fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(crate) fn copied(&self) -> bool {
+        self.copied
+    }
+}
+
+/// Block until the kernel releases every pending buffer in `tracker`,
+/// feeding it error queue entries from `recv`. Should the error queue fail
+/// or `FLUSH_TIMEOUT` pass the pending buffers are leaked, since the kernel
+/// may still read them.
+pub(crate) fn flush<B, F>(fd: RawFd, mut recv: F, tracker: &mut Tracker<B>)
+where
+    F: FnMut(&mut [u8]) -> Result<(usize, SockAddr)>,
+{
+    let deadline = Instant::now() + FLUSH_TIMEOUT;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if drain(&mut recv, tracker).is_err() {
+            break;
+        } else if tracker.pending() == 0 {
+            return;
+        } else if left.is_zero() || poll(fd, Some(left)).is_err() {
+            break;
+        }
+    }
+    mem::forget(mem::take(&mut tracker.pending));
+}
+
+/// Wait until `fd` has an error queue entry, or any other event, for up
+/// to `timeout` if given.
+fn poll(fd: RawFd, timeout: Option<Duration>) -> Result<()> {
+    let timeout = timeout.map_or(-1, |t| t.as_millis().min(c_int::MAX as u128) as c_int);
+    let mut pfd = libc::pollfd { fd, events: 0, revents: 0 };
+    if unsafe { libc::poll(&mut pfd, 1, timeout) } < 0 {
+        let err = Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Feed error queue entries from `recv` to `tracker` until it would block.
+pub(crate) fn drain<B, F>(mut recv: F, tracker: &mut Tracker<B>) -> Result<()>
+where
+    F: FnMut(&mut [u8]) -> Result<(usize, SockAddr)>,
+{
+    let mut ctrl = [0u8; 128];
+    loop {
+        ctrl.iter_mut().for_each(|b| *b = 0);
+        match recv(&mut ctrl) {
+            Ok(..)                                          => (),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+            Err(e)                                          => return Err(e),
+        }
+
+        tracker.record(&ctrl);
+    }
+}